@@ -5,16 +5,29 @@
 //!
 //! An element inserted into an `OptionVec<T>` will occupy the first available
 //! position in the container.
+//!
+//! `GenOptionVec<T>` is a variant which identifies elements by `Key` and
+//! rejects keys whose elements have since been removed.
 
 #![deny(missing_docs)]
 
-use std::cmp::Ordering;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, TryReserveError};
+use std::error::Error;
 use std::fmt;
+use std::hash::Hash;
 use std::iter::{self, FromIterator};
-use std::ops;
+use std::ops::{self, Bound, ControlFlow, Range, RangeBounds};
 use std::slice;
+use std::sync::Arc;
 use std::vec;
 
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+
 /// An abstraction over `Vec<Option<T>>`
 ///
 /// An element in an `OptionVec<T>` can be accessed by index and maintains
@@ -22,8 +35,46 @@ use std::vec;
 ///
 /// An element inserted into an `OptionVec<T>` will occupy the first available
 /// position in the container.
+///
+/// # Length and capacity
+///
+/// An `OptionVec<T>` has three related sizes:
+///
+/// * `occupied_len` (or its alias, `len`) is the number of contained elements.
+/// * `slot_len` is the number of positions, whether occupied or `None`.
+/// * `capacity` is the number of positions allocated.
+///
+/// These always satisfy `occupied_len() <= slot_len() <= capacity()`.
+///
+/// The number of contained elements is tracked as elements are inserted and
+/// removed, so `len`, `occupied_len`, and `is_empty` are `O(1)`.
+///
+/// Vacant positions are likewise tracked, so `insert` finds the first
+/// available position in `O(log N)` time rather than by scanning.
+/// Occupied positions are recorded in a bitmap, so `iter`, `enumerate`,
+/// `drain_keep_layout`, and methods which look for the first or last element
+/// skip `None` positions 64 at a time. Other iterators examine each position.
+///
+/// # Memory layout
+///
+/// Each position is stored as an `Option<T>`, with no additional
+/// per-position data. For element types with a niche, such as `NonZeroU32`,
+/// references, or `Box<T>`, a position therefore occupies no more space than
+/// the element itself. Any bookkeeping the container keeps, such as its
+/// list of vacant positions, is stored separately rather than alongside
+/// each position.
 pub struct OptionVec<T> {
     vec: Vec<Option<T>>,
+    len: usize,
+    /// Vacant indices, lowest first. Entries which have since been filled
+    /// or truncated are discarded when found; every `None` position within
+    /// `vec` has at least one entry.
+    free: BinaryHeap<Reverse<usize>>,
+    /// Occupancy of each position, one bit per position. Bits beyond the end
+    /// of `vec` are clear, and missing words are treated as clear.
+    bits: Vec<u64>,
+    growth_guard: Option<usize>,
+    exact_growth: bool,
 }
 
 impl<T> OptionVec<T> {
@@ -38,7 +89,139 @@ impl<T> OptionVec<T> {
     pub fn with_capacity(n: usize) -> OptionVec<T> {
         OptionVec{
             vec: Vec::with_capacity(n),
+            len: 0,
+            free: BinaryHeap::new(),
+            bits: Vec::new(),
+            growth_guard: None,
+            exact_growth: false,
+        }
+    }
+
+    /// Creates an `OptionVec<T>` with `n` positions, each of which is
+    /// assigned the result of calling `f` with its index.
+    pub fn from_fn_sparse<F>(n: usize, f: F) -> OptionVec<T>
+            where F: FnMut(usize) -> Option<T> {
+        OptionVec::from((0..n).map(f).collect::<Vec<_>>())
+    }
+
+    /// Creates an `OptionVec<T>` from `(index, element)` pairs in strictly
+    /// ascending order of index.
+    ///
+    /// Positions between indices are filled with `None`.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if indices are not strictly ascending.
+    pub fn from_sorted_pairs<I>(iter: I) -> OptionVec<T>
+            where I: IntoIterator<Item=(usize, T)> {
+        let mut v = OptionVec::new();
+
+        for (idx, t) in iter {
+            debug_assert!(idx >= v.vec.len(),
+                "index {} is not in ascending order", idx);
+
+            while v.vec.len() < idx {
+                v.vec.push(None);
+            }
+            v.push(t);
+        }
+
+        v.reindex();
+        v
+    }
+
+    /// Creates an `OptionVec<T>` from `(index, element)` pairs, handling
+    /// duplicate indices according to `on_conflict`.
+    ///
+    /// An error is returned only if `on_conflict` is `ConflictPolicy::Error`
+    /// and an index occurs more than once.
+    pub fn collect_indexed<I>(iter: I, on_conflict: ConflictPolicy)
+            -> Result<OptionVec<T>, IndexMapError>
+            where I: IntoIterator<Item=(usize, T)> {
+        let mut v = OptionVec::new();
+
+        for (idx, t) in iter {
+            if v.contains(idx) {
+                match on_conflict {
+                    ConflictPolicy::KeepFirst => continue,
+                    ConflictPolicy::KeepLast => (),
+                    ConflictPolicy::Error => return Err(IndexMapError::Collision(idx))
+                }
+            }
+
+            v.insert_at(idx, t);
+        }
+
+        Ok(v)
+    }
+
+    /// Creates an `OptionVec<T>` from run-length encoded segments, such as
+    /// those returned by `to_rle`.
+    pub fn from_rle<I>(segments: I) -> OptionVec<T>
+            where I: IntoIterator<Item=RleSegment<T>> {
+        let mut v = OptionVec::new();
+
+        for seg in segments {
+            match seg {
+                RleSegment::Holes(n) => {
+                    let len = v.vec.len();
+                    v.vec.resize_with(len + n, || None);
+                }
+                RleSegment::Values(values) => {
+                    v.len += values.len();
+                    v.vec.extend(values.into_iter().map(Some));
+                }
+            }
+        }
+
+        v.reindex();
+        v
+    }
+
+    /// Creates an `OptionVec<T>` from its positions and a list of the
+    /// indices of its `None` positions, such as one previously saved
+    /// alongside the positions.
+    ///
+    /// An error is returned unless `free` contains the index of every `None`
+    /// position exactly once and no other indices.
+    pub fn from_parts_with_free_list(vec: Vec<Option<T>>, free: Vec<usize>)
+            -> Result<OptionVec<T>, PartsError> {
+        let mut listed = vec![false; vec.len()];
+
+        for &idx in &free {
+            match vec.get(idx) {
+                None => return Err(PartsError::OutOfBounds(idx)),
+                Some(&Some(_)) => return Err(PartsError::Occupied(idx)),
+                Some(&None) if listed[idx] => return Err(PartsError::Duplicate(idx)),
+                Some(&None) => listed[idx] = true,
+            }
+        }
+
+        if let Some(idx) = vec.iter().zip(&listed).position(|(v, &l)| v.is_none() && !l) {
+            return Err(PartsError::Missing(idx));
         }
+
+        let mut v = OptionVec{
+            len: vec.len() - free.len(),
+            vec,
+            free: free.into_iter().map(Reverse).collect(),
+            bits: Vec::new(),
+            growth_guard: None,
+            exact_growth: false,
+        };
+
+        v.rebuild_bits();
+        Ok(v)
+    }
+
+    /// Places an element at the given position and returns the container.
+    ///
+    /// This is equivalent to `insert_at`, allowing a container to be built
+    /// in a single expression.
+    #[inline]
+    pub fn with_value_at(mut self, idx: usize, t: T) -> OptionVec<T> {
+        self.insert_at(idx, t);
+        self
     }
 
     /// Returns a borrowed reference to the internal `Vec<Option<T>>`.
@@ -47,13 +230,127 @@ impl<T> OptionVec<T> {
         &self.vec
     }
 
-    /// Returns a mutable reference to the internal `Vec<Option<T>>`.
+    /// Returns the internal slots as a slice of `Option<T>`.
+    ///
+    /// Unlike indexing the container directly, `slots()[idx]` yields
+    /// `&Option<T>` and does not panic when the slot at `idx` is empty.
+    #[inline]
+    pub fn slots(&self) -> &[Option<T>] {
+        &self.vec
+    }
+
+    /// Returns a shareable copy of the container's slots.
+    ///
+    /// Any trailing `None` elements are not included.
+    pub fn snapshot(&self) -> Arc<[Option<T>]>
+            where T: Clone {
+        let end = self.end_occupied();
+        Arc::from(&self.vec[..end])
+    }
+
+    /// Replaces the contents of the container with a copy of the given
+    /// slots, such as those returned by `snapshot`.
+    pub fn restore(&mut self, snap: &[Option<T>])
+            where T: Clone {
+        self.vec.clear();
+        self.vec.extend_from_slice(snap);
+        self.recount();
+        self.reindex();
+    }
+
+    /// Converts the container into a `Vec<T>` if every position holds
+    /// an element.
+    ///
+    /// If any position is `None`, the container is returned unchanged as
+    /// an error.
+    pub fn try_into_dense(self) -> Result<Vec<T>, OptionVec<T>> {
+        if self.len == self.vec.len() {
+            Ok(self.vec.into_iter().flatten().collect())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Converts the container into a `Vec<T>` of its elements, sorted.
+    pub fn into_sorted_vec(self) -> Vec<T>
+            where T: Ord {
+        let mut v: Vec<T> = self.into_iter().collect();
+        v.sort();
+        v
+    }
+
+    /// Converts the container into a `Vec<T>` of its elements, sorted
+    /// using the given comparison function.
+    pub fn into_sorted_by<F>(self, compare: F) -> Vec<T>
+            where F: FnMut(&T, &T) -> Ordering {
+        let mut v: Vec<T> = self.into_iter().collect();
+        v.sort_by(compare);
+        v
+    }
+
+    /// Converts the container into a `Vec<T>` of its elements, sorted
+    /// by the key returned by the given function.
+    pub fn into_sorted_by_key<K, F>(self, f: F) -> Vec<T>
+            where K: Ord, F: FnMut(&T) -> K {
+        let mut v: Vec<T> = self.into_iter().collect();
+        v.sort_by_key(f);
+        v
+    }
+
+    /// Converts the container into a `Vec` of indices and a `Vec` of the
+    /// elements at those indices, in ascending order of index.
+    pub fn unzip(self) -> (Vec<usize>, Vec<T>) {
+        self.into_enumerate().unzip()
+    }
+
+    /// Removes and returns the leading run of elements which precedes the
+    /// first `None` position.
+    ///
+    /// The remaining positions are shifted toward the front, so the index of
+    /// each remaining element decreases by the length of the returned `Vec`.
+    pub fn split_dense_prefix(&mut self) -> Vec<T> {
+        let n = self.vec.iter().position(Option::is_none)
+            .unwrap_or(self.vec.len());
+
+        self.len -= n;
+        let prefix = self.vec.drain(..n).flatten().collect();
+
+        self.reindex();
+        prefix
+    }
+
+    /// Returns the slots of the container as run-length encoded segments.
+    ///
+    /// Each segment is either a run of consecutive `None` positions or a run
+    /// of consecutive elements. Trailing `None` positions are included.
+    pub fn to_rle(&self) -> Vec<RleSegment<T>>
+            where T: Clone {
+        let mut segs = Vec::new();
+
+        for v in &self.vec {
+            match (v, segs.last_mut()) {
+                (None, Some(RleSegment::Holes(n))) => *n += 1,
+                (Some(t), Some(RleSegment::Values(values))) => values.push(t.clone()),
+                (None, _) => segs.push(RleSegment::Holes(1)),
+                (Some(t), _) => segs.push(RleSegment::Values(vec![t.clone()])),
+            }
+        }
+
+        segs
+    }
+
+    /// Returns a guard which dereferences to the internal `Vec<Option<T>>`.
     ///
     /// Modification of this internal container is safe, but using methods
     /// such as `Vec::insert` or `Vec::remove` will invalidate existing indices.
+    ///
+    /// When the guard is dropped, contained elements are recounted and the
+    /// record of vacant and occupied positions used by `insert` and `iter`
+    /// is rebuilt, which is an `O(N)` operation. No further call is needed
+    /// to restore consistency after modifying the positions.
     #[inline]
-    pub fn inner_mut(&mut self) -> &mut Vec<Option<T>> {
-        &mut self.vec
+    pub fn inner_mut(&mut self) -> InnerMut<'_, T> {
+        InnerMut(self)
     }
 
     /// Returns the allocated capacity for elements.
@@ -64,43 +361,254 @@ impl<T> OptionVec<T> {
 
     /// Returns the number of contained elements.
     ///
-    /// This operation is `O(N)`, as all non-`None` elements must be individually
-    /// counted.
+    /// This is an alias for `occupied_len`.
     #[inline]
     pub fn len(&self) -> usize {
-        self.vec.iter().filter(|v| v.is_some()).count()
+        self.occupied_len()
     }
 
-    /// Returns whether the container is empty.
+    /// Returns the number of contained elements.
+    #[inline]
+    pub fn occupied_len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of slots in the container, whether occupied or not.
+    #[inline]
+    pub fn slot_len(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// Returns the ratio of contained elements to positions.
     ///
-    /// This operation is `O(N)` worst-case, as any elements must be searched
-    /// for a non-`None` element.
+    /// A container with no positions has a density of `1.0`.
+    pub fn density(&self) -> f64 {
+        if self.vec.is_empty() {
+            1.0
+        } else {
+            self.occupied_len() as f64 / self.vec.len() as f64
+        }
+    }
+
+    /// Returns whether the container is empty.
     #[inline]
     pub fn is_empty(&self) -> bool {
-        self.vec.iter().all(|v| v.is_none())
+        self.len == 0
+    }
+
+    /// Checks that the cached length, vacant positions, and occupancy bitmap
+    /// agree with the positions they describe.
+    ///
+    /// These are maintained by every method, so an error indicates a bug in
+    /// this crate.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let actual = self.vec.iter().filter(|v| v.is_some()).count();
+
+        if self.len != actual {
+            return Err(ValidationError::Length{cached: self.len, actual});
+        }
+
+        let free = self.free.iter().map(|&Reverse(i)| i).collect::<HashSet<_>>();
+
+        for (i, v) in self.vec.iter().enumerate() {
+            if v.is_none() && !free.contains(&i) {
+                return Err(ValidationError::MissingFree(i));
+            }
+        }
+
+        for (w, &word) in self.bits.iter().enumerate() {
+            for b in 0..64 {
+                let idx = w * 64 + b;
+                let occupied = self.vec.get(idx).is_some_and(|v| v.is_some());
+
+                if (word >> b & 1 != 0) != occupied {
+                    return Err(ValidationError::Bitmap(idx));
+                }
+            }
+        }
+
+        if let Some(idx) = self.vec.iter().skip(self.bits.len() * 64)
+                .position(|v| v.is_some()) {
+            return Err(ValidationError::Bitmap(self.bits.len() * 64 + idx));
+        }
+
+        Ok(())
     }
 
     /// Inserts an element into the first available position, returning the
     /// destination position.
+    ///
+    /// This operation is `O(log N)` in the number of `None` positions.
     #[inline]
     pub fn insert(&mut self, t: T) -> usize {
         if let Some(pos) = self.first_vacant() {
+            self.free.pop();
             self.vec[pos] = Some(t);
+            self.len += 1;
+            self.set_bit(pos);
             pos
         } else {
             self.push(t)
         }
     }
 
+    /// Inserts an element at the given position, returning the element
+    /// previously at that position, if any.
+    ///
+    /// If `idx` is beyond the end of the container, it is extended with
+    /// `None` elements. If exact growth is enabled (see `grow_exact`),
+    /// only as much capacity as is needed is allocated.
+    ///
+    /// # Panics
+    ///
+    /// If a growth guard is set (see `set_growth_guard`) and extending the
+    /// container to reach `idx` would exceed it.
+    pub fn insert_at(&mut self, idx: usize, t: T) -> Option<T> {
+        if idx >= self.vec.len() {
+            self.check_growth(idx + 1);
+
+            if self.exact_growth {
+                self.vec.reserve_exact(idx + 1 - self.vec.len());
+            }
+
+            let len = self.vec.len();
+            self.vec.resize_with(idx + 1, || None);
+            self.free_slots(len..idx);
+        }
+
+        let old = self.vec[idx].replace(t);
+
+        if old.is_none() {
+            self.len += 1;
+            self.set_bit(idx);
+        }
+
+        old
+    }
+
+    /// Fills the first run of `n` consecutive `None` positions with elements
+    /// produced by calling `fill` with each index, returning the index at
+    /// which the run begins.
+    ///
+    /// If there is no such run, the container is extended with `None`
+    /// elements to create one at its end.
+    pub fn allocate_contiguous<F>(&mut self, n: usize, mut fill: F) -> usize
+            where F: FnMut(usize) -> T {
+        let mut start = 0;
+        let mut run = 0;
+
+        for (i, v) in self.vec.iter().enumerate() {
+            if run == n {
+                break;
+            }
+
+            if v.is_some() {
+                start = i + 1;
+                run = 0;
+            } else {
+                run += 1;
+            }
+        }
+
+        let end = start + n;
+
+        if end > self.vec.len() {
+            self.vec.resize_with(end, || None);
+        }
+
+        for i in start..end {
+            self.vec[i] = Some(fill(i));
+            self.len += 1;
+            self.set_bit(i);
+        }
+
+        start
+    }
+
+    /// Removes the elements of the `n` positions beginning at `start`,
+    /// returning the previous contents of each position.
+    ///
+    /// Positions beyond the end of the container are not included in the
+    /// returned `Vec`.
+    pub fn free_contiguous(&mut self, start: usize, n: usize) -> Vec<Option<T>> {
+        let end = start.saturating_add(n).min(self.vec.len());
+        (start..end).map(|i| self.remove(i)).collect()
+    }
+
+    /// Replaces the element at the given position if it satisfies `pred`,
+    /// returning the previous element.
+    ///
+    /// If the position is empty or `pred` returns `false`, the container is
+    /// not modified and `value` is returned as an error.
+    pub fn replace_if<P>(&mut self, idx: usize, pred: P, value: T) -> Result<Option<T>, T>
+            where P: FnOnce(&T) -> bool {
+        match self.vec.get_mut(idx) {
+            Some(v) if v.as_ref().is_some_and(pred) => Ok(v.replace(value)),
+            _ => Err(value)
+        }
+    }
+
+    /// Sets a guard against accidental large growth in `insert_at`.
+    ///
+    /// When set to `Some(factor)`, `insert_at` will panic rather than grow
+    /// the container to more than `factor` times its current slot count
+    /// (or `factor` slots, if the container has none). `None` disables
+    /// the guard, which is the default.
+    #[inline]
+    pub fn set_growth_guard(&mut self, factor: Option<usize>) {
+        self.growth_guard = factor;
+    }
+
+    /// Sets whether `insert_at` reserves exactly the capacity it needs when
+    /// extending the container, rather than growing amortized.
+    ///
+    /// This is disabled by default.
+    #[inline]
+    pub fn grow_exact(&mut self, enabled: bool) {
+        self.exact_growth = enabled;
+    }
+
     /// Removes an element from the given position, if one exists.
     #[inline]
     pub fn remove(&mut self, idx: usize) -> Option<T> {
-        self.vec.get_mut(idx).and_then(|v| v.take())
+        let t = self.vec.get_mut(idx)?.take()?;
+        self.len -= 1;
+        self.clear_bit(idx);
+        self.free_slots(iter::once(idx));
+        Some(t)
+    }
+
+    /// Resizes the container to `new_slot_len` positions.
+    ///
+    /// If the container grows, new positions are filled with clones of
+    /// `value`. If it shrinks, positions beyond `new_slot_len` are removed.
+    pub fn resize_filled(&mut self, new_slot_len: usize, value: T)
+            where T: Clone {
+        self.truncate_slots(new_slot_len);
+        let len = self.vec.len();
+
+        self.len += new_slot_len - len;
+        self.vec.resize(new_slot_len, Some(value));
+        (len..new_slot_len).for_each(|i| self.set_bit(i));
+    }
+
+    /// Resizes the container to `new_slot_len` positions.
+    ///
+    /// If the container grows, new positions are filled with `T::default()`.
+    /// If it shrinks, positions beyond `new_slot_len` are removed.
+    pub fn resize_with_default(&mut self, new_slot_len: usize)
+            where T: Default {
+        self.truncate_slots(new_slot_len);
+        let len = self.vec.len();
+
+        self.len += new_slot_len - len;
+        self.vec.resize_with(new_slot_len, || Some(T::default()));
+        (len..new_slot_len).for_each(|i| self.set_bit(i));
     }
 
     /// Reserves capacity for at least `n` more elements.
     pub fn reserve(&mut self, n: usize) {
-        let rem_cap = self.capacity() - self.len();
+        let rem_cap = self.capacity() - self.occupied_len();
 
         if rem_cap < n {
             self.vec.reserve(n - rem_cap);
@@ -109,13 +617,91 @@ impl<T> OptionVec<T> {
 
     /// Reserves capacity for exactly `n` more elements.
     pub fn reserve_exact(&mut self, n: usize) {
-        let rem_cap = self.capacity() - self.len();
+        let rem_cap = self.capacity() - self.occupied_len();
 
         if rem_cap < n {
             self.vec.reserve_exact(n - rem_cap);
         }
     }
 
+    /// Inserts all elements from an iterator of known length.
+    ///
+    /// Unlike `extend`, exactly as much capacity is reserved as is needed to
+    /// hold elements which do not fit into existing `None` positions.
+    pub fn extend_exact<I>(&mut self, iter: I)
+            where I: IntoIterator<Item=T>, I::IntoIter: ExactSizeIterator {
+        let iter = iter.into_iter();
+        let vacant = self.vec.len() - self.occupied_len();
+
+        self.vec.reserve_exact(iter.len().saturating_sub(vacant));
+
+        for v in iter {
+            self.insert(v);
+        }
+    }
+
+    /// Inserts all elements from an iterator, returning their positions.
+    ///
+    /// Capacity for the lower bound of the iterator's `size_hint` is reserved
+    /// before any element is inserted. If this reservation fails, the
+    /// container is not modified and the error is returned along with the
+    /// unconsumed iterator. Elements beyond the lower bound are inserted
+    /// as by `insert`.
+    pub fn try_insert_many<I>(&mut self, values: I)
+            -> Result<Vec<usize>, (TryReserveError, I::IntoIter)>
+            where I: IntoIterator<Item=T> {
+        let iter = values.into_iter();
+        let (low, _) = iter.size_hint();
+        let vacant = self.vec.len() - self.occupied_len();
+
+        let mut indices = Vec::new();
+
+        if let Err(e) = self.vec.try_reserve(low.saturating_sub(vacant)) {
+            return Err((e, iter));
+        }
+        if let Err(e) = indices.try_reserve(low) {
+            return Err((e, iter));
+        }
+
+        for v in iter {
+            indices.push(self.insert(v));
+        }
+
+        Ok(indices)
+    }
+
+    /// Reserves capacity for slots up to and including `max_index`.
+    ///
+    /// The number of slots is not changed.
+    ///
+    /// # Panics
+    ///
+    /// If the new capacity overflows `usize`.
+    pub fn reserve_for_index(&mut self, max_index: usize) {
+        let end = max_index.checked_add(1).expect("capacity overflow");
+        let n = end.saturating_sub(self.vec.len());
+        self.vec.reserve_exact(n);
+    }
+
+    /// Reserves capacity for enough slots to hold `expected_elements`
+    /// at the given density.
+    ///
+    /// Capacity is reserved for a total of
+    /// `ceil(expected_elements / target_density)` slots.
+    /// The number of slots is not changed.
+    ///
+    /// # Panics
+    ///
+    /// If `target_density` is not greater than `0.0` and at most `1.0`.
+    pub fn reserve_for_density(&mut self, expected_elements: usize, target_density: f64) {
+        assert!(target_density > 0.0 && target_density <= 1.0,
+            "target density {} is not within (0, 1]", target_density);
+
+        let slots = (expected_elements as f64 / target_density).ceil() as usize;
+        let n = slots.saturating_sub(self.vec.len());
+        self.vec.reserve_exact(n);
+    }
+
     /// Shrinks the allocation as much as possible.
     ///
     /// Any trailing `None` elements will be truncated. `None` elements in
@@ -126,6 +712,185 @@ impl<T> OptionVec<T> {
 
         self.vec.truncate(n);
         self.vec.shrink_to_fit();
+        self.shrink_free_list();
+    }
+
+    /// Shrinks the bookkeeping used to find `None` positions and occupied
+    /// elements, discarding stale entries and releasing excess capacity.
+    ///
+    /// This is useful after a phase of many removals and insertions.
+    /// `shrink_to_fit` also does this.
+    pub fn shrink_free_list(&mut self) {
+        self.rebuild_free();
+        self.free.shrink_to_fit();
+        self.bits.truncate(self.vec.len().div_ceil(64));
+        self.bits.shrink_to_fit();
+    }
+
+    /// Shrinks the allocation as much as possible, while keeping at least
+    /// `min_slots` positions.
+    ///
+    /// Trailing `None` elements are truncated as in `shrink_to_fit`, but not
+    /// below `min_slots`. If there are fewer than `min_slots` positions,
+    /// the container is extended with `None` elements.
+    pub fn shrink_to_fit_keeping(&mut self, min_slots: usize) {
+        let n = self.end_occupied().max(min_slots);
+        let len = self.vec.len();
+
+        self.vec.resize_with(n, || None);
+        self.vec.shrink_to_fit();
+        self.free_slots(len..n);
+    }
+
+    /// Moves elements from the end of the container into `None` positions
+    /// nearer the front until `density() >= target` or no `None` positions
+    /// remain, truncating trailing `None` elements.
+    ///
+    /// Returns a list of `(old_index, new_index)` for each element moved,
+    /// in the order moves were made. Elements which are not moved keep
+    /// their indices.
+    pub fn prune_to_density(&mut self, target: f64) -> Vec<(usize, usize)> {
+        let len = self.occupied_len();
+        let mut moves = Vec::new();
+        let mut hole = 0;
+
+        let end = self.end_occupied();
+        self.vec.truncate(end);
+
+        while (len as f64) < target * self.vec.len() as f64 {
+            match self.vec[hole..].iter().position(Option::is_none) {
+                Some(n) => hole += n,
+                None => break
+            }
+
+            let last = self.vec.len() - 1;
+
+            self.vec.swap(hole, last);
+            self.set_bit(hole);
+            self.clear_bit(last);
+            moves.push((last, hole));
+
+            let end = self.end_occupied();
+            self.vec.truncate(end);
+        }
+
+        moves
+    }
+
+    /// Moves all elements to the front of the container, removing any
+    /// `None` elements, and returns the number of `None` elements removed.
+    ///
+    /// The relative order of elements is preserved, but any existing indices
+    /// are invalidated.
+    pub fn compact_count(&mut self) -> usize {
+        let n = self.vec.len();
+
+        self.vec.retain(Option::is_some);
+        self.reindex();
+        n - self.vec.len()
+    }
+
+    /// Moves all elements to the front of the container, removing any
+    /// `None` elements, and calls `on_move(old_index, new_index)` as each
+    /// element is moved.
+    ///
+    /// The relative order of elements is preserved. Elements which do not
+    /// change position are not reported.
+    pub fn compact_with<F>(&mut self, mut on_move: F)
+            where F: FnMut(usize, usize) {
+        let mut slots = self.inner_mut();
+        let mut new = 0;
+
+        for old in 0..slots.len() {
+            if slots[old].is_some() {
+                if old != new {
+                    slots.swap(old, new);
+                    on_move(old, new);
+                }
+                new += 1;
+            }
+        }
+
+        slots.truncate(new);
+    }
+
+    /// Moves all elements to the front of the container, removing any
+    /// `None` elements, and writes a mapping from each old index to its new
+    /// index into `remap`.
+    ///
+    /// The relative order of elements is preserved. `remap` is cleared and
+    /// then filled with the new index of each old position's element, or
+    /// `None` for positions that were empty, so that a buffer can be reused
+    /// across calls.
+    pub fn compact_into_remap(&mut self, remap: &mut Vec<Option<usize>>) {
+        let mut new = 0;
+
+        remap.clear();
+        remap.reserve(self.vec.len());
+
+        for old in 0..self.vec.len() {
+            if self.vec[old].is_some() {
+                self.vec.swap(old, new);
+                remap.push(Some(new));
+                new += 1;
+            } else {
+                remap.push(None);
+            }
+        }
+
+        self.vec.truncate(new);
+        self.reindex();
+    }
+
+    /// Moves all elements to the front of the container, in the order given
+    /// by `cmp`, removing any `None` elements.
+    ///
+    /// The sort is stable. Returns a mapping from each old index to the
+    /// new index of its element, or `None` for positions that were empty.
+    pub fn compact_by<F>(&mut self, mut cmp: F) -> Vec<Option<usize>>
+            where F: FnMut(&T, &T) -> Ordering {
+        let mut slots = self.inner_mut();
+        let mut remap = vec![None; slots.len()];
+        let mut elems = slots.drain(..).enumerate()
+            .filter_map(|(i, v)| v.map(|v| (i, v)))
+            .collect::<Vec<_>>();
+
+        elems.sort_by(|a, b| cmp(&a.1, &b.1));
+
+        for (new, (old, v)) in elems.into_iter().enumerate() {
+            remap[old] = Some(new);
+            slots.push(Some(v));
+        }
+
+        remap
+    }
+
+    /// Moves all elements to the front of the container, in ascending order
+    /// of the key given by `key`, removing any `None` elements.
+    ///
+    /// The sort is stable. Returns a list of `(old_index, new_index)` for
+    /// each element which changed position, in ascending order of new index.
+    pub fn compact_by_key<K, F>(&mut self, mut key: F) -> Vec<(usize, usize)>
+            where K: Ord, F: FnMut(&T) -> K {
+        let remap = self.compact_by(|a, b| key(a).cmp(&key(b)));
+
+        let mut moves = remap.into_iter().enumerate()
+            .filter_map(|(old, new)| new.map(|new| (old, new)))
+            .filter(|&(old, new)| old != new)
+            .collect::<Vec<_>>();
+
+        moves.sort_by_key(|&(_, new)| new);
+        moves
+    }
+
+    /// Sorts all positions, including `None` positions, with a comparator.
+    ///
+    /// The sort is stable. Since positions are reordered, any existing
+    /// indices are invalidated.
+    #[inline]
+    pub fn sort_slots_by<F>(&mut self, cmp: F)
+            where F: FnMut(&Option<T>, &Option<T>) -> Ordering {
+        self.inner_mut().sort_by(cmp);
     }
 
     /// Retains only elements specified by the predicate.
@@ -134,7 +899,11 @@ impl<T> OptionVec<T> {
     /// to `None`.
     pub fn retain<F>(&mut self, mut f: F)
             where F: FnMut(&mut T) -> bool {
-        for v in &mut self.vec {
+        // Length and free positions are rebuilt when `slots` is dropped,
+        // including when `f` panics.
+        let mut slots = self.inner_mut();
+
+        for v in slots.iter_mut() {
             let retain = match *v {
                 Some(ref mut inner) => f(inner),
                 None => true
@@ -146,628 +915,4362 @@ impl<T> OptionVec<T> {
         }
     }
 
-    /// Removes and returns the last occupied element.
-    #[inline]
-    pub fn pop_back(&mut self) -> Option<T> {
-        if let Some(pos) = self.last_occupied() {
-            self.remove(pos)
-        } else {
-            None
-        }
+    /// Retains only elements specified by the predicate, then truncates
+    /// any trailing `None` elements.
+    ///
+    /// Unlike `shrink_to_fit`, the allocated capacity is not released.
+    pub fn retain_trim<F>(&mut self, f: F)
+            where F: FnMut(&mut T) -> bool {
+        self.retain(f);
+
+        let n = self.end_occupied();
+        self.vec.truncate(n);
     }
 
-    /// Removes and returns the first occupied element.
-    #[inline]
-    pub fn pop_front(&mut self) -> Option<T> {
-        if let Some(pos) = self.first_occupied() {
-            self.remove(pos)
-        } else {
-            None
+    /// Retains only elements specified by the predicate, then calls
+    /// `shrink_to_fit` if `density()` has fallen below `shrink_below`.
+    pub fn retain_bounded<F>(&mut self, f: F, shrink_below: f64)
+            where F: FnMut(&mut T) -> bool {
+        self.retain(f);
+
+        if self.density() < shrink_below {
+            self.shrink_to_fit();
         }
     }
 
-    /// Removes all contained elements.
+    /// Retains only elements specified by the predicate, truncates any
+    /// trailing `None` elements as in `retain_trim`, and returns the resulting
+    /// number of positions.
     #[inline]
-    pub fn clear(&mut self) {
-        self.vec.clear();
+    pub fn retain_returning_slot_len<F>(&mut self, f: F) -> usize
+            where F: FnMut(&mut T) -> bool {
+        self.retain_trim(f);
+        self.vec.len()
     }
 
-    /// Returns whether an element exists at the given index.
-    #[inline]
-    pub fn contains(&self, idx: usize) -> bool {
-        self.vec.get(idx).map_or(false, |v| v.is_some())
-    }
+    /// Retains only elements specified by the predicate, stopping early if
+    /// the predicate returns `ControlFlow::Break`.
+    ///
+    /// All elements `e` such that `f(&mut e)` returns `Continue(false)` will be
+    /// assigned to `None`. If `f` returns `Break(b)`, the element and all
+    /// elements following it are retained and `Some(b)` is returned.
+    pub fn try_retain<B, F>(&mut self, mut f: F) -> Option<B>
+            where F: FnMut(&mut T) -> ControlFlow<B, bool> {
+        let mut slots = self.inner_mut();
+        let mut res = None;
+
+        for v in slots.iter_mut() {
+            let retain = match *v {
+                Some(ref mut inner) => match f(inner) {
+                    ControlFlow::Continue(retain) => retain,
+                    ControlFlow::Break(b) => {
+                        res = Some(b);
+                        break;
+                    }
+                },
+                None => true
+            };
 
-    /// Returns an element at the given position.
-    #[inline]
-    pub fn get(&self, idx: usize) -> Option<&T> {
-        self.vec.get(idx).and_then(|v| v.as_ref())
-    }
+            if !retain {
+                *v = None;
+            }
+        }
 
-    /// Returns a mutable reference to an element at the given position.
-    #[inline]
-    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
-        self.vec.get_mut(idx).and_then(|v| v.as_mut())
+        res
     }
 
-    /// Returns an iterator over contained elements.
-    #[inline]
-    pub fn iter(&self) -> Iter<T> {
-        Iter(self.vec.iter())
-    }
+    /// Retains only elements specified by the predicate, visiting elements
+    /// in descending order of index.
+    ///
+    /// All elements `e` such that `f(&mut e)` returns `false` will be assigned
+    /// to `None`.
+    pub fn retain_rev<F>(&mut self, mut f: F)
+            where F: FnMut(&mut T) -> bool {
+        for v in self.inner_mut().iter_mut().rev() {
+            let retain = match *v {
+                Some(ref mut inner) => f(inner),
+                None => true
+            };
 
-    /// Returns an iterator over mutable references to contained elements.
-    #[inline]
-    pub fn iter_mut(&mut self) -> IterMut<T> {
-        IterMut(self.vec.iter_mut())
+            if !retain {
+                *v = None;
+            }
+        }
     }
 
-    /// Returns an iterator over values with indices.
+    /// Retains only elements specified by the predicate, evaluating the
+    /// predicate on multiple threads.
     ///
-    /// The elements yielded by this iterator will be `(usize, T)`.
-    #[inline]
-    pub fn into_enumerate(self) -> IntoEnumerate<T> {
-        IntoEnumerate(self.vec.into_iter().enumerate())
+    /// All elements `e` such that `f(&mut e)` returns `false` will be assigned
+    /// to `None`. Elements are not visited in any particular order.
+    ///
+    /// Requires the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    pub fn par_retain<F>(&mut self, f: F)
+            where T: Send, F: Fn(&mut T) -> bool + Sync {
+        self.inner_mut().par_iter_mut().for_each(|v| {
+            let retain = match *v {
+                Some(ref mut inner) => f(inner),
+                None => true
+            };
+
+            if !retain {
+                *v = None;
+            }
+        });
     }
 
-    /// Returns an iterator over references with indices.
+    /// Retains only elements whose indices are specified by the predicate.
     ///
-    /// The elements yielded by this iterator will be `(usize, &T)`.
-    #[inline]
-    pub fn enumerate(&self) -> Enumerate<T> {
-        Enumerate(self.vec.iter().enumerate())
+    /// All elements at an index `i` such that `f(i)` returns `false` will be
+    /// assigned to `None`. Elements are not passed to the predicate.
+    pub fn retain_keys<F>(&mut self, mut f: F)
+            where F: FnMut(usize) -> bool {
+        for (i, v) in self.inner_mut().iter_mut().enumerate() {
+            if v.is_some() && !f(i) {
+                *v = None;
+            }
+        }
     }
 
-    /// Returns an iterator over mutable references with indices.
+    /// Retains only elements specified by the predicate, passing each
+    /// removed element and its index to `on_remove`.
     ///
-    /// The elements yielded by this iterator will be `(usize, &mut T)`.
-    #[inline]
-    pub fn enumerate_mut(&mut self) -> EnumerateMut<T> {
-        EnumerateMut(self.vec.iter_mut().enumerate())
-    }
+    /// All elements `e` such that `keep(&mut e)` returns `false` will be
+    /// assigned to `None`.
+    pub fn retain_tombstoned<F, G>(&mut self, mut keep: F, mut on_remove: G)
+            where F: FnMut(&mut T) -> bool, G: FnMut(usize, T) {
+        for (i, v) in self.inner_mut().iter_mut().enumerate() {
+            let retain = match *v {
+                Some(ref mut inner) => keep(inner),
+                None => true
+            };
 
-    fn first_vacant(&self) -> Option<usize> {
-        for (i, v) in self.vec.iter().enumerate() {
-            if v.is_none() {
-                return Some(i);
+            if !retain {
+                if let Some(t) = v.take() {
+                    on_remove(i, t);
+                }
             }
         }
-        None
     }
 
-    fn first_occupied(&self) -> Option<usize> {
-        for (i, v) in self.vec.iter().enumerate() {
-            if v.is_some() {
-                return Some(i);
+    /// Removes all elements for which `pred` returns `true`, returning them
+    /// with their indices in ascending order of index.
+    pub fn remove_where<F>(&mut self, mut pred: F) -> Vec<(usize, T)>
+            where F: FnMut(&T) -> bool {
+        let mut removed = Vec::new();
+
+        self.retain_tombstoned(|t| !pred(t), |i, t| removed.push((i, t)));
+        removed
+    }
+
+    /// Retains only elements specified by the predicate, reporting the
+    /// indices of elements kept and removed.
+    ///
+    /// All elements `e` such that `f(&mut e)` returns `false` will be assigned
+    /// to `None`.
+    pub fn retain_report<F>(&mut self, mut f: F) -> RetainReport
+            where F: FnMut(&mut T) -> bool {
+        let mut report = RetainReport::default();
+
+        for (i, v) in self.inner_mut().iter_mut().enumerate() {
+            let retain = match *v {
+                Some(ref mut inner) => f(inner),
+                None => continue
+            };
+
+            if retain {
+                report.kept.push(i);
+            } else {
+                report.removed.push(i);
+                *v = None;
             }
         }
-        None
-    }
 
-    fn end_occupied(&self) -> usize {
-        self.last_occupied().map_or(0, |n| n + 1)
+        report
     }
 
-    fn last_occupied(&self) -> Option<usize> {
-        for (i, v) in self.vec.iter().enumerate().rev() {
-            if v.is_some() {
-                return Some(i);
+    /// Retains and transforms elements in place.
+    ///
+    /// Each element is passed by value to `f`. If `f` returns `Some(_)`,
+    /// the new value is stored at the same position; otherwise, the position
+    /// is assigned to `None`.
+    pub fn retain_map<F>(&mut self, mut f: F)
+            where F: FnMut(T) -> Option<T> {
+        for v in self.inner_mut().iter_mut() {
+            if let Some(inner) = v.take() {
+                *v = f(inner);
             }
         }
-        None
     }
 
-    fn push(&mut self, t: T) -> usize {
-        let n = self.vec.len();
-        self.vec.push(Some(t));
-        n
+    /// Keeps, removes, or replaces each element in place.
+    ///
+    /// Each element is passed, along with its index, to `f`. The returned
+    /// `Action` determines whether the element is kept, its position is
+    /// assigned to `None`, or it is replaced with a new value.
+    pub fn update_retain<F>(&mut self, mut f: F)
+            where F: FnMut(usize, &mut T) -> Action<T> {
+        for (i, v) in self.inner_mut().iter_mut().enumerate() {
+            let action = match *v {
+                Some(ref mut t) => f(i, t),
+                None => continue
+            };
+
+            match action {
+                Action::Keep => (),
+                Action::Remove => *v = None,
+                Action::Replace(t) => *v = Some(t),
+            }
+        }
     }
-}
 
-/// An owned iterator of `OptionVec<T>` elements.
-pub struct IntoIter<T>(vec::IntoIter<Option<T>>);
+    /// Swaps the positions within two ranges, element for element,
+    /// including `None` positions.
+    ///
+    /// The ranges must be of equal length, must not overlap, and must be
+    /// within the container's positions.
+    pub fn swap_ranges(&mut self, a: Range<usize>, b: Range<usize>) -> Result<(), RangeError> {
+        if a.start > a.end || b.start > b.end ||
+                a.end > self.vec.len() || b.end > self.vec.len() {
+            return Err(RangeError::OutOfBounds);
+        }
+        if a.len() != b.len() {
+            return Err(RangeError::LengthMismatch);
+        }
 
-/// An iterator of borrowed `OptionVec<T>` elements.
-pub struct Iter<'a, T: 'a>(slice::Iter<'a, Option<T>>);
+        let (lo, hi) = if a.start <= b.start { (a, b) } else { (b, a) };
 
-/// An iterator of mutable `OptionVec<T>` elements.
-#[derive(Debug)]
-pub struct IterMut<'a, T: 'a>(slice::IterMut<'a, Option<T>>);
+        if !lo.is_empty() && lo.end > hi.start {
+            return Err(RangeError::Overlapping);
+        }
 
-/// An enumerated owned iterator of `OptionVec<T>` elements, yielding `(usize, T)`.
-#[derive(Debug)]
-pub struct IntoEnumerate<T>(iter::Enumerate<vec::IntoIter<Option<T>>>);
+        let n = lo.len();
+        let (x, y) = self.vec.split_at_mut(hi.start);
 
-/// An enumerated iterator of borrowed `OptionVec<T>` elements, yielding `(usize, &T)`.
-#[derive(Debug)]
-pub struct Enumerate<'a, T: 'a>(iter::Enumerate<slice::Iter<'a, Option<T>>>);
+        x[lo.clone()].swap_with_slice(&mut y[..n]);
+        self.reindex_range(lo);
+        self.reindex_range(hi);
+        Ok(())
+    }
 
-/// An enumerated iterator of mutable `OptionVec<T>` elements, yielding `(usize, &mut T)`.
-#[derive(Debug)]
-pub struct EnumerateMut<'a, T: 'a>(iter::Enumerate<slice::IterMut<'a, Option<T>>>);
+    /// Replaces the positions within a range with the given positions,
+    /// returning the replaced positions.
+    ///
+    /// The range must be within the container's positions and `src` must
+    /// be of the same length as the range.
+    pub fn try_replace_range<R>(&mut self, range: R, src: Vec<Option<T>>)
+            -> Result<Vec<Option<T>>, ReplaceError>
+            where R: RangeBounds<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(&n) => Some(n),
+            Bound::Excluded(&n) => n.checked_add(1),
+            Bound::Unbounded => Some(0)
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n.checked_add(1),
+            Bound::Excluded(&n) => Some(n),
+            Bound::Unbounded => Some(self.vec.len())
+        };
+
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) if start <= end && end <= self.vec.len() =>
+                (start, end),
+            _ => return Err(ReplaceError::OutOfBounds)
+        };
+
+        if src.len() != end - start {
+            return Err(ReplaceError::LengthMismatch);
+        }
 
-macro_rules! option_vec_iter {
-    ( $name:ident , $r:ty , $pat:pat , $v:expr ) => {
-        impl<'a, T: 'a> Iterator for $name<'a, T> {
-            type Item = $r;
+        let added = src.iter().filter(|v| v.is_some()).count();
+        let replaced: Vec<_> = self.vec.splice(start..end, src).collect();
 
-            fn next(&mut self) -> Option<$r> {
-                while let Some(v) = self.0.next() {
-                    if let $pat = v {
-                        return Some($v);
-                    }
-                }
+        self.len = self.len + added - replaced.iter().filter(|v| v.is_some()).count();
+        self.reindex_range(start..end);
+        Ok(replaced)
+    }
 
-                None
-            }
+    /// Moves each element within the given range by `offset` positions,
+    /// leaving `None` in positions which are not refilled.
+    ///
+    /// Every destination must be within the container's positions and must
+    /// not hold an element from outside the range. If an error is returned,
+    /// the container is not modified.
+    pub fn shift_within(&mut self, range: Range<usize>, offset: isize) -> Result<(), ShiftError> {
+        if range.start > range.end || range.end > self.vec.len() {
+            return Err(ShiftError::OutOfBounds);
+        }
 
-            fn size_hint(&self) -> (usize, Option<usize>) {
-                let (_, max) = self.0.size_hint();
-                (0, max)
+        for i in range.clone() {
+            if self.vec[i].is_none() {
+                continue;
             }
-        }
 
-        impl<'a, T: 'a> DoubleEndedIterator for $name<'a, T> {
-            fn next_back(&mut self) -> Option<$r> {
-                while let Some(v) = self.0.next_back() {
-                    if let $pat = v {
-                        return Some($v);
-                    }
-                }
+            let dest = match i.checked_add_signed(offset) {
+                Some(dest) if dest < self.vec.len() => dest,
+                _ => return Err(ShiftError::OutOfBounds)
+            };
 
-                None
+            if !range.contains(&dest) && self.vec[dest].is_some() {
+                return Err(ShiftError::Collision(dest));
             }
         }
-    }
-}
 
-impl<T> Iterator for IntoIter<T> {
-    type Item = T;
+        let moved: Vec<_> = self.vec[range.clone()].iter_mut()
+            .enumerate()
+            .filter_map(|(i, v)| v.take().map(|t| (i + range.start, t)))
+            .collect();
 
-    fn next(&mut self) -> Option<T> {
-        while let Some(v) = self.0.next() {
-            if let Some(v) = v {
-                return Some(v);
-            }
+        for (i, t) in moved {
+            self.vec[i.wrapping_add_signed(offset)] = Some(t);
         }
 
-        None
+        let start = range.start.saturating_add_signed(offset.min(0));
+        let end = range.end.saturating_add_signed(offset.max(0)).min(self.vec.len());
+
+        self.reindex_range(start..end);
+        Ok(())
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let (_, max) = self.0.size_hint();
-        (0, max)
+    /// Removes and returns the last occupied element.
+    #[inline]
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.pop_back_indexed().map(|(_, t)| t)
     }
-}
 
-impl<T> DoubleEndedIterator for IntoIter<T> {
-    fn next_back(&mut self) -> Option<T> {
-        while let Some(v) = self.0.next_back() {
-            if let Some(v) = v {
-                return Some(v);
-            }
-        }
+    /// Removes and returns the first occupied element.
+    #[inline]
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.pop_front_indexed().map(|(_, t)| t)
+    }
 
-        None
+    /// Removes and returns the last occupied element with its index.
+    #[inline]
+    pub fn pop_back_indexed(&mut self) -> Option<(usize, T)> {
+        let pos = self.last_occupied()?;
+        self.remove(pos).map(|t| (pos, t))
     }
-}
 
-option_vec_iter!{ Iter, &'a T, &Some(ref v), v }
-option_vec_iter!{ IterMut, &'a mut T, &mut Some(ref mut v), v }
+    /// Removes and returns the first occupied element with its index.
+    #[inline]
+    pub fn pop_front_indexed(&mut self) -> Option<(usize, T)> {
+        let pos = self.first_occupied()?;
+        self.remove(pos).map(|t| (pos, t))
+    }
 
-impl<T> Iterator for IntoEnumerate<T> {
-    type Item = (usize, T);
+    /// Removes and returns the first occupied element, moving the last
+    /// occupied element into its position.
+    ///
+    /// To remove the last occupied element, use `pop_back`; no element needs
+    /// to be moved to keep the front of the container occupied.
+    pub fn swap_remove_front(&mut self) -> Option<T> {
+        let first = self.first_occupied()?;
+        let last = self.last_occupied()?;
+
+        self.vec.swap(first, last);
+        self.remove(last)
+    }
 
-    fn next(&mut self) -> Option<(usize, T)> {
-        while let Some((n, v)) = self.0.next() {
-            if let Some(v) = v {
-                return Some((n, v));
+    /// Removes and returns the element at the given index, moving the last
+    /// occupied element into its position.
+    ///
+    /// If an element was moved, its old and new indices are also returned,
+    /// as `(old, new)`. No element is moved if the removed element was the
+    /// last occupied element.
+    pub fn swap_remove_indexed_back(&mut self, idx: usize)
+            -> Option<(T, Option<(usize, usize)>)> {
+        let t = self.remove(idx)?;
+        let last = self.last_occupied();
+
+        match last {
+            Some(last) if last > idx => {
+                self.vec.swap(idx, last);
+                self.set_bit(idx);
+                self.clear_bit(last);
+                self.free_slots(iter::once(last));
+                Some((t, Some((last, idx))))
             }
+            _ => Some((t, None))
         }
-
-        None
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let (_, max) = self.0.size_hint();
-        (0, max)
+    /// Removes all contained elements.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.vec.clear();
+        self.len = 0;
+        self.free.clear();
+        self.bits.clear();
     }
-}
 
-option_vec_iter!{ Enumerate, (usize, &'a T), (n, &Some(ref v)), (n, v) }
-option_vec_iter!{ EnumerateMut, (usize, &'a mut T), (n, &mut Some(ref mut v)), (n, v) }
+    /// Returns whether an element exists at the given index.
+    #[inline]
+    pub fn contains(&self, idx: usize) -> bool {
+        self.vec.get(idx).is_some_and(|v| v.is_some())
+    }
 
-impl<T: fmt::Debug> fmt::Debug for IntoIter<T> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_tuple("IntoIter")
-            .field(&self.0.as_slice())
-            .finish()
+    /// Returns whether the given index is within the container's positions,
+    /// whether or not an element exists at the index.
+    #[inline]
+    pub fn is_in_bounds(&self, idx: usize) -> bool {
+        idx < self.vec.len()
     }
-}
 
-impl<'a, T: 'a> Clone for Iter<'a, T> {
-    fn clone(&self) -> Iter<'a, T> {
-        Iter(self.0.clone())
+    /// Returns whether no element exists at any index within the given range.
+    ///
+    /// Indices beyond the end of the container are considered vacant.
+    pub fn is_range_vacant<R>(&self, range: R) -> bool
+            where R: RangeBounds<usize> {
+        let (start, end) = self.clamp_range(range);
+        self.vec[start..end].iter().all(Option::is_none)
     }
-}
 
-impl<'a, T: 'a + fmt::Debug> fmt::Debug for Iter<'a, T> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_tuple("IntoIter")
-            .field(&self.0.as_slice())
-            .finish()
+    /// Returns the number of consecutive `None` positions beginning at `idx`.
+    ///
+    /// Only positions within the container are counted; the result never
+    /// extends beyond `slot_len()`.
+    pub fn vacant_run_at(&self, idx: usize) -> usize {
+        self.vec.get(idx..).map_or(0, |s| s.iter().take_while(|v| v.is_none()).count())
     }
-}
 
-impl<T: fmt::Debug> fmt::Debug for OptionVec<T> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_map()
-            .entries(self.vec.iter()
-                .enumerate().filter(|&(_idx, v)| v.is_some()))
-            .finish()
+    /// Returns the number of consecutive elements beginning at `idx`.
+    pub fn occupied_run_at(&self, idx: usize) -> usize {
+        self.vec.get(idx..).map_or(0, |s| s.iter().take_while(|v| v.is_some()).count())
     }
-}
 
-impl<'a, T: 'a> Clone for Enumerate<'a, T> {
-    fn clone(&self) -> Enumerate<'a, T> {
+    /// Returns histograms of the lengths of runs of consecutive elements
+    /// and of consecutive `None` positions.
+    ///
+    /// In each returned `Vec`, the value at index `n` is the number of runs
+    /// of length `n`. The first `Vec` counts runs of elements and the second
+    /// counts runs of `None` positions.
+    pub fn run_histogram(&self) -> (Vec<usize>, Vec<usize>) {
+        fn record(hist: &mut Vec<usize>, n: usize) {
+            if hist.len() <= n {
+                hist.resize(n + 1, 0);
+            }
+            hist[n] += 1;
+        }
+
+        let mut occupied = Vec::new();
+        let mut vacant = Vec::new();
+        let mut run = 0;
+
+        for (i, v) in self.vec.iter().enumerate() {
+            run += 1;
+
+            let end = match self.vec.get(i + 1) {
+                Some(next) => next.is_some() != v.is_some(),
+                None => true
+            };
+
+            if end {
+                record(if v.is_some() { &mut occupied } else { &mut vacant }, run);
+                run = 0;
+            }
+        }
+
+        (occupied, vacant)
+    }
+
+    /// Returns the indices of the first and last elements, or `None` if the
+    /// container is empty.
+    pub fn occupied_bounds(&self) -> Option<(usize, usize)> {
+        Some((self.first_occupied()?, self.last_occupied()?))
+    }
+
+    /// Returns an element at the given position.
+    #[inline]
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        self.vec.get(idx).and_then(|v| v.as_ref())
+    }
+
+    /// Returns a mutable reference to an element at the given position.
+    #[inline]
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        self.vec.get_mut(idx).and_then(|v| v.as_mut())
+    }
+
+    /// Returns an entry for in-place manipulation of the given position.
+    #[inline]
+    pub fn entry(&mut self, idx: usize) -> Entry<'_, T> {
+        if self.contains(idx) {
+            Entry::Occupied(OccupiedEntry{vec: self, idx})
+        } else {
+            Entry::Vacant(VacantEntry{vec: self, idx})
+        }
+    }
+
+    /// Moves each element to a new position, as given by `mapping` from
+    /// current index to new index.
+    ///
+    /// If any element has no entry in `mapping`, or if two elements would
+    /// be moved to the same position, an error is returned and the container
+    /// is not modified.
+    ///
+    /// # Panics
+    ///
+    /// If an element would be moved to index `usize::MAX`.
+    pub fn rehome(&mut self, mapping: &HashMap<usize, usize>) -> Result<(), RehomeError> {
+        let mut seen = HashSet::new();
+        let mut targets = Vec::new();
+
+        for (i, v) in self.vec.iter().enumerate() {
+            if v.is_some() {
+                let new = *mapping.get(&i).ok_or(RehomeError::Missing(i))?;
+
+                if !seen.insert(new) {
+                    return Err(RehomeError::Collision(new));
+                }
+
+                targets.push(new);
+            }
+        }
+
+        self.relocate(targets);
+        Ok(())
+    }
+
+    /// Moves each element from its index `i` to the index `f(i)`.
+    ///
+    /// The container is extended with `None` elements as needed to hold
+    /// the new indices. If two elements would be moved to the same position,
+    /// an error is returned and the container is not modified.
+    ///
+    /// # Panics
+    ///
+    /// If an element would be moved to index `usize::MAX`.
+    pub fn map_indices<F>(&mut self, mut f: F) -> Result<(), IndexMapError>
+            where F: FnMut(usize) -> usize {
+        let mut seen = HashSet::new();
+        let mut targets = Vec::new();
+
+        for (i, v) in self.vec.iter().enumerate() {
+            if v.is_some() {
+                let new = f(i);
+
+                if !seen.insert(new) {
+                    return Err(IndexMapError::Collision(new));
+                }
+
+                targets.push(new);
+            }
+        }
+
+        self.relocate(targets);
+        Ok(())
+    }
+
+    /// Calls `f` with a mutable reference to the element at each of the
+    /// given indices, in order.
+    ///
+    /// Indices at which no element is present are skipped.
+    pub fn for_each_index_mut<I, F>(&mut self, indices: I, mut f: F)
+            where I: IntoIterator<Item=usize>, F: FnMut(usize, &mut T) {
+        for idx in indices {
+            if let Some(t) = self.get_mut(idx) {
+                f(idx, t);
+            }
+        }
+    }
+
+    /// Returns a mutable reference to an element at the given position,
+    /// or an error describing why no element is present.
+    pub fn index_mut_checked(&mut self, idx: usize) -> Result<&mut T, GetError> {
+        match self.vec.get_mut(idx) {
+            Some(&mut Some(ref mut t)) => Ok(t),
+            Some(&mut None) => Err(GetError::Empty(idx)),
+            None => Err(GetError::OutOfBounds(idx)),
+        }
+    }
+
+    /// Merges the elements of two containers, each of which is sorted,
+    /// into a new container with no `None` positions.
+    ///
+    /// Where elements compare equal, those of `self` precede those of `other`.
+    pub fn merge_sorted(self, other: OptionVec<T>) -> OptionVec<T>
+            where T: Ord {
+        let mut a = self.into_iter().peekable();
+        let mut b = other.into_iter().peekable();
+        let mut vec = Vec::new();
+
+        loop {
+            let take_a = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) => x <= y,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break
+            };
+
+            vec.push(if take_a { a.next() } else { b.next() });
+        }
+
+        OptionVec::from(vec)
+    }
+
+    /// Returns references to the elements at each of the given positions,
+    /// or an error describing the first position at which no element is
+    /// present.
+    ///
+    /// Indices may be repeated.
+    pub fn get_many<const N: usize>(&self, indices: [usize; N]) -> Result<[&T; N], GetError> {
+        for &idx in &indices {
+            match self.vec.get(idx) {
+                Some(&Some(_)) => (),
+                Some(&None) => return Err(GetError::Empty(idx)),
+                None => return Err(GetError::OutOfBounds(idx)),
+            }
+        }
+
+        Ok(indices.map(|idx| &self[idx]))
+    }
+
+    /// Returns an iterator over contained elements.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter{
+            iter: self.vec.iter(),
+            bits: &self.bits,
+            front: 0,
+            last: None,
+            remaining: self.len,
+        }
+    }
+
+    /// Returns an iterator over mutable references to contained elements.
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut{
+            iter: self.vec.iter_mut(),
+            remaining: self.len,
+        }
+    }
+
+    /// Returns an iterator which removes and yields all elements with their
+    /// indices, leaving `None` in each position.
+    ///
+    /// The elements yielded by this iterator will be `(usize, T)`.
+    /// The number of positions is not changed. If the iterator is dropped
+    /// before it is exhausted, the remaining elements are also removed.
+    #[inline]
+    pub fn drain_keep_layout(&mut self) -> DrainKeepLayout<'_, T> {
+        let back = self.vec.len();
+
+        DrainKeepLayout{
+            vec: self,
+            front: 0,
+            back,
+        }
+    }
+
+    /// Returns an iterator over values, in descending order of index.
+    #[inline]
+    pub fn into_iter_rev(self) -> iter::Rev<IntoIter<T>> {
+        self.into_iter().rev()
+    }
+
+    /// Returns an iterator over values with indices.
+    ///
+    /// The elements yielded by this iterator will be `(usize, T)`.
+    #[inline]
+    pub fn into_enumerate(self) -> IntoEnumerate<T> {
+        IntoEnumerate(self.vec.into_iter().enumerate())
+    }
+
+    /// Returns an iterator over references with indices, for elements whose
+    /// indices fall within the given range.
+    ///
+    /// The elements yielded by this iterator will be `(usize, &T)`.
+    /// Any part of the range beyond the end of the container is ignored.
+    pub fn iter_range<R>(&self, range: R) -> impl DoubleEndedIterator<Item=(usize, &T)>
+            where R: RangeBounds<usize> {
+        let (start, end) = self.clamp_range(range);
+
+        self.vec[start..end].iter().enumerate()
+            .filter_map(move |(i, v)| v.as_ref().map(|v| (start + i, v)))
+    }
+
+    /// Returns an iterator over the indices of elements within the given
+    /// range.
+    ///
+    /// Any part of the range beyond the end of the container is ignored.
+    pub fn occupied_indices_range<R>(&self, range: R) -> impl DoubleEndedIterator<Item=usize> + '_
+            where R: RangeBounds<usize> {
+        let (start, end) = self.clamp_range(range);
+
+        (start..end).filter(move |&i| self.vec[i].is_some())
+    }
+
+    /// Returns an iterator over the indices of `None` positions within the
+    /// given range.
+    ///
+    /// Any part of the range beyond the end of the container is ignored.
+    pub fn vacant_indices_range<R>(&self, range: R) -> impl DoubleEndedIterator<Item=usize> + '_
+            where R: RangeBounds<usize> {
+        let (start, end) = self.clamp_range(range);
+
+        (start..end).filter(move |&i| self.vec[i].is_none())
+    }
+
+    /// Returns an iterator over references with both their position among
+    /// contained elements and their index.
+    ///
+    /// The elements yielded by this iterator will be `(usize, usize, &T)`,
+    /// where the first value counts contained elements from zero and the
+    /// second is the element's index.
+    #[inline]
+    pub fn iter_positioned(&self) -> impl Iterator<Item=(usize, usize, &T)> {
+        self.enumerate().enumerate().map(|(pos, (i, t))| (pos, i, t))
+    }
+
+    /// Returns an iterator over groups of consecutive elements which share
+    /// the same key.
+    ///
+    /// The groups yielded by this iterator will be `Vec<(usize, &T)>`.
+    /// A `None` position ends a group, even if the elements on either
+    /// side of it have equal keys.
+    pub fn chunk_by<'a, K, F>(&'a self, mut key: F)
+            -> impl Iterator<Item=Vec<(usize, &'a T)>> + 'a
+            where K: PartialEq + 'a, F: FnMut(&T) -> K + 'a {
+        let mut iter = self.vec.iter().enumerate();
+        let mut pending = None;
+
+        iter::from_fn(move || {
+            let (i, t, k) = match pending.take() {
+                Some(p) => p,
+                None => loop {
+                    if let (i, Some(t)) = iter.next()? {
+                        let k = key(t);
+                        break (i, t, k);
+                    }
+                }
+            };
+
+            let mut group = vec![(i, t)];
+
+            for (j, v) in iter.by_ref() {
+                match v {
+                    Some(u) => {
+                        let ku = key(u);
+
+                        if ku != k {
+                            pending = Some((j, u, ku));
+                            break;
+                        }
+
+                        group.push((j, u));
+                    }
+                    None => break
+                }
+            }
+
+            Some(group)
+        })
+    }
+
+    /// Returns an iterator over values with indices, which reports its
+    /// exact length.
+    ///
+    /// The elements yielded by this iterator will be `(usize, T)`.
+    pub fn into_keyed_iter(self) -> KeyedIntoIter<T> {
+        KeyedIntoIter{
+            iter: self.vec.into_iter().enumerate(),
+            remaining: self.len,
+        }
+    }
+
+    /// Returns an iterator over values, which reports its exact length.
+    ///
+    /// This is equivalent to `into_iter`, as `IntoIter` also reports
+    /// its exact length.
+    #[inline]
+    pub fn into_iter_exact(self) -> impl ExactSizeIterator<Item=T> + DoubleEndedIterator {
+        self.into_iter()
+    }
+
+    /// Returns an iterator over references with indices.
+    ///
+    /// The elements yielded by this iterator will be `(usize, &T)`.
+    #[inline]
+    pub fn enumerate(&self) -> Enumerate<'_, T> {
+        Enumerate(self.iter())
+    }
+
+    /// Returns an iterator over mutable references with indices.
+    ///
+    /// The elements yielded by this iterator will be `(usize, &mut T)`.
+    #[inline]
+    pub fn enumerate_mut(&mut self) -> EnumerateMut<'_, T> {
+        EnumerateMut(self.vec.iter_mut().enumerate())
+    }
+
+    /// Returns an iterator over every slot with its index, including empty
+    /// slots.
+    ///
+    /// The elements yielded by this iterator will be `(usize, Option<&T>)`.
+    #[inline]
+    pub fn iter_with_holes(&self) -> impl DoubleEndedIterator<Item=(usize, Option<&T>)> {
+        self.vec.iter().map(Option::as_ref).enumerate()
+    }
+
+    /// Creates a new container from `(index, element)` pairs produced
+    /// by calling `f` on each element and its index.
+    ///
+    /// If multiple elements are produced for the same index, the last one
+    /// is retained.
+    pub fn flat_map_indexed<U, I, F>(&self, mut f: F) -> OptionVec<U>
+            where I: IntoIterator<Item=(usize, U)>, F: FnMut(usize, &T) -> I {
+        let mut v = OptionVec::new();
+
+        for (i, t) in self.enumerate() {
+            for (idx, u) in f(i, t) {
+                v.insert_at(idx, u);
+            }
+        }
+
+        v
+    }
+
+    /// Returns the first element, with its index, for which `pred` returns
+    /// `Ok(true)`.
+    ///
+    /// If `pred` returns an error, the search stops and the error is returned.
+    pub fn try_find<E, P>(&self, mut pred: P) -> Result<Option<(usize, &T)>, E>
+            where P: FnMut(&T) -> Result<bool, E> {
+        for (i, t) in self.enumerate() {
+            if pred(t)? {
+                return Ok(Some((i, t)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns a string of all elements, formatted with `Display` and
+    /// separated by `sep`.
+    pub fn join(&self, sep: &str) -> String
+            where T: fmt::Display {
+        use std::fmt::Write;
+
+        let mut s = String::new();
+
+        for (i, t) in self.iter().enumerate() {
+            if i != 0 {
+                s.push_str(sep);
+            }
+            let _ = write!(s, "{}", t);
+        }
+
+        s
+    }
+
+    /// Returns the least element, or `None` if the container is empty.
+    ///
+    /// If several elements are equally minimum, the one with the lowest
+    /// index is returned.
+    #[inline]
+    pub fn iter_min(&self) -> Option<&T>
+            where T: Ord {
+        self.iter().min()
+    }
+
+    /// Returns the greatest element, or `None` if the container is empty.
+    ///
+    /// If several elements are equally maximum, the one with the lowest
+    /// index is returned.
+    #[inline]
+    pub fn iter_max(&self) -> Option<&T>
+            where T: Ord {
+        // `Iterator::max` returns the last of equal elements
+        self.iter().rev().max()
+    }
+
+    /// Returns the index of the element with the least key, as given by `f`,
+    /// or `None` if the container is empty.
+    ///
+    /// If several elements are equally minimum, the lowest index is returned.
+    #[inline]
+    pub fn argmin_by_key<K, F>(&self, mut f: F) -> Option<usize>
+            where K: Ord, F: FnMut(&T) -> K {
+        self.enumerate().min_by_key(|&(_, t)| f(t)).map(|(i, _)| i)
+    }
+
+    /// Returns the index of the element with the greatest key, as given
+    /// by `f`, or `None` if the container is empty.
+    ///
+    /// If several elements are equally maximum, the lowest index is returned.
+    #[inline]
+    pub fn argmax_by_key<K, F>(&self, mut f: F) -> Option<usize>
+            where K: Ord, F: FnMut(&T) -> K {
+        // `Iterator::max_by_key` returns the last of equal elements
+        self.enumerate().rev().max_by_key(|&(_, t)| f(t)).map(|(i, _)| i)
+    }
+
+    /// Returns the running value of an accumulator at the index of each
+    /// element.
+    ///
+    /// The accumulator begins as `init` and is updated by `f` with each
+    /// element, in ascending order of index.
+    pub fn prefix_scan<B, F>(&self, init: B, mut f: F) -> Vec<(usize, B)>
+            where B: Clone, F: FnMut(&B, &T) -> B {
+        let mut acc = init;
+        let mut res = Vec::new();
+
+        for (i, t) in self.enumerate() {
+            acc = f(&acc, t);
+            res.push((i, acc.clone()));
+        }
+
+        res
+    }
+
+    /// Returns a map of each element's index to a reference to the element.
+    #[inline]
+    pub fn to_btreemap(&self) -> BTreeMap<usize, &T> {
+        self.enumerate().collect()
+    }
+
+    /// Returns a map of each distinct element to the indices at which
+    /// it occurs, in ascending order.
+    pub fn value_index_map(&self) -> HashMap<&T, Vec<usize>>
+            where T: Eq + Hash {
+        let mut map = HashMap::new();
+
+        for (i, t) in self.enumerate() {
+            map.entry(t).or_insert_with(Vec::new).push(i);
+        }
+
+        map
+    }
+
+    /// Returns the number of elements which are equal to an element at
+    /// a lower index.
+    pub fn duplicate_count(&self) -> usize
+            where T: Eq + Hash {
+        let mut seen = HashSet::new();
+
+        self.iter().filter(|&t| !seen.insert(t)).count()
+    }
+
+    /// Returns the differences between this container and `other`.
+    ///
+    /// Each `SlotDiff` describes a position at which `other` differs from
+    /// `self`, in ascending order of position. References held by the diff
+    /// point into `other`.
+    pub fn diff<'a>(&self, other: &'a OptionVec<T>) -> Vec<SlotDiff<'a, T>>
+            where T: PartialEq {
+        let n = self.vec.len().max(other.vec.len());
+        let mut diff = Vec::new();
+
+        for idx in 0..n {
+            match (self.get(idx), other.get(idx)) {
+                (None, Some(b)) => diff.push(SlotDiff::Added(idx, b)),
+                (Some(_), None) => diff.push(SlotDiff::Removed(idx)),
+                (Some(a), Some(b)) if a != b => diff.push(SlotDiff::Changed(idx, b)),
+                _ => ()
+            }
+        }
+
+        diff
+    }
+
+    /// Applies differences produced by `diff`, cloning added and changed
+    /// elements into this container.
+    ///
+    /// After `a.apply_diff(a.diff(&b))`, each position in `a` holds
+    /// the same element as in `b`.
+    pub fn apply_diff(&mut self, patch: Vec<SlotDiff<'_, T>>)
+            where T: Clone {
+        for d in patch {
+            match d {
+                SlotDiff::Added(idx, t) | SlotDiff::Changed(idx, t) => {
+                    self.insert_at(idx, t.clone());
+                }
+                SlotDiff::Removed(idx) => {
+                    self.remove(idx);
+                }
+            }
+        }
+    }
+
+    /// Returns a view which, when iterated, yields mutable references
+    /// with indices.
+    ///
+    /// The elements yielded by iterating this view will be `(usize, &mut T)`.
+    #[inline]
+    pub fn indexed_mut(&mut self) -> IndexedMutView<'_, T> {
+        IndexedMutView(self)
+    }
+
+    /// Moves each element, in order, to the corresponding distinct index
+    /// in `targets`.
+    ///
+    /// # Panics
+    ///
+    /// If any index in `targets` is `usize::MAX`. The container is not
+    /// modified.
+    fn relocate(&mut self, targets: Vec<usize>) {
+        let end = targets.iter().max()
+            .map_or(0, |&n| n.checked_add(1).expect("capacity overflow"));
+
+        let mut vec = Vec::with_capacity(end);
+        vec.resize_with(end, || None);
+
+        for (v, new) in self.vec.drain(..).flatten().zip(targets) {
+            vec[new] = Some(v);
+        }
+
+        self.vec = vec;
+        self.reindex();
+    }
+
+    fn clamp_range<R>(&self, range: R) -> (usize, usize)
+            where R: RangeBounds<usize> {
+        let len = self.vec.len();
+
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n.saturating_add(1),
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => len
+        }.min(len);
+
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n.saturating_add(1),
+            Bound::Unbounded => 0
+        }.min(end);
+
+        (start, end)
+    }
+
+    fn recount(&mut self) {
+        self.len = self.vec.iter().filter(|v| v.is_some()).count();
+    }
+
+    /// Rebuilds the free list and occupancy bitmap from the positions.
+    fn reindex(&mut self) {
+        self.rebuild_free();
+        self.rebuild_bits();
+    }
+
+    fn rebuild_bits(&mut self) {
+        self.bits.clear();
+        self.bits.resize(self.vec.len().div_ceil(64), 0);
+
+        for (i, v) in self.vec.iter().enumerate() {
+            if v.is_some() {
+                self.bits[i / 64] |= 1 << (i % 64);
+            }
+        }
+    }
+
+    fn rebuild_free(&mut self) {
+        self.free.clear();
+        self.free.extend(self.vec.iter().enumerate()
+            .filter(|&(_, v)| v.is_none())
+            .map(|(i, _)| Reverse(i)));
+    }
+
+    /// Records the given indices as vacant.
+    fn free_slots<I>(&mut self, indices: I)
+            where I: IntoIterator<Item=usize> {
+        self.free.extend(indices.into_iter().map(Reverse));
+
+        // Discard stale entries once they outnumber positions
+        if self.free.len() > 2 * self.vec.len() {
+            self.rebuild_free();
+        }
+    }
+
+    /// Updates the occupancy bitmap for positions within the given range
+    /// and records any `None` positions as vacant.
+    fn reindex_range(&mut self, range: Range<usize>) {
+        let mut vacant = Vec::new();
+
+        for i in range {
+            if self.vec[i].is_some() {
+                self.set_bit(i);
+            } else {
+                self.clear_bit(i);
+                vacant.push(i);
+            }
+        }
+
+        self.free_slots(vacant);
+    }
+
+    fn set_bit(&mut self, idx: usize) {
+        let w = idx / 64;
+
+        if w >= self.bits.len() {
+            self.bits.resize(w + 1, 0);
+        }
+        self.bits[w] |= 1 << (idx % 64);
+    }
+
+    fn clear_bit(&mut self, idx: usize) {
+        if let Some(word) = self.bits.get_mut(idx / 64) {
+            *word &= !(1 << (idx % 64));
+        }
+    }
+
+    /// Truncates the container to `n` positions, removing any elements
+    /// beyond them.
+    fn truncate_slots(&mut self, n: usize) {
+        if let Some(tail) = self.vec.get(n..) {
+            self.len -= tail.iter().filter(|v| v.is_some()).count();
+            self.vec.truncate(n);
+
+            self.bits.truncate(n.div_ceil(64));
+
+            if let Some(word) = self.bits.get_mut(n / 64) {
+                *word &= (1 << (n % 64)) - 1;
+            }
+        }
+    }
+
+    fn check_growth(&self, new_len: usize) {
+        if let Some(factor) = self.growth_guard {
+            let limit = self.vec.len().max(1).saturating_mul(factor);
+
+            if new_len > limit {
+                panic!("growth to {} slots exceeds guard of {} slots", new_len, limit);
+            }
+        }
+    }
+
+    /// Returns the lowest vacant index, which remains at the top of `free`.
+    fn first_vacant(&mut self) -> Option<usize> {
+        while let Some(&Reverse(i)) = self.free.peek() {
+            if self.vec.get(i).is_some_and(Option::is_none) {
+                return Some(i);
+            }
+            self.free.pop();
+        }
+        None
+    }
+
+    fn first_occupied(&self) -> Option<usize> {
+        next_set_bit(&self.bits, 0, self.vec.len())
+    }
+
+    fn end_occupied(&self) -> usize {
+        self.last_occupied().map_or(0, |n| n + 1)
+    }
+
+    fn last_occupied(&self) -> Option<usize> {
+        prev_set_bit(&self.bits, 0, self.vec.len())
+    }
+
+    fn push(&mut self, t: T) -> usize {
+        let n = self.vec.len();
+        self.vec.push(Some(t));
+        self.len += 1;
+        self.set_bit(n);
+        n
+    }
+}
+
+/// Returns the lowest index within `start..end` whose bit is set.
+fn next_set_bit(bits: &[u64], start: usize, end: usize) -> Option<usize> {
+    let mut i = start;
+
+    while i < end {
+        let w = i / 64;
+        let word = *bits.get(w)? >> (i % 64);
+
+        if word != 0 {
+            let idx = i + word.trailing_zeros() as usize;
+            return if idx < end { Some(idx) } else { None };
+        }
+
+        i = (w + 1) * 64;
+    }
+
+    None
+}
+
+/// Returns the highest index within `start..end` whose bit is set.
+fn prev_set_bit(bits: &[u64], start: usize, end: usize) -> Option<usize> {
+    let mut i = end;
+
+    while i > start {
+        let w = (i - 1) / 64;
+        let word = bits.get(w).map_or(0, |&word| word & (!0 >> (63 - (i - 1) % 64)));
+
+        if word != 0 {
+            let idx = w * 64 + 63 - word.leading_zeros() as usize;
+            return if idx >= start { Some(idx) } else { None };
+        }
+
+        i = w * 64;
+    }
+
+    None
+}
+
+/// An owned iterator of `OptionVec<T>` elements.
+pub struct IntoIter<T> {
+    iter: vec::IntoIter<Option<T>>,
+    remaining: usize,
+}
+
+/// An iterator of borrowed `OptionVec<T>` elements.
+pub struct Iter<'a, T: 'a> {
+    iter: slice::Iter<'a, Option<T>>,
+    bits: &'a [u64],
+    front: usize,
+    last: Option<usize>,
+    remaining: usize,
+}
+
+/// An iterator of mutable `OptionVec<T>` elements.
+#[derive(Debug)]
+pub struct IterMut<'a, T: 'a> {
+    iter: slice::IterMut<'a, Option<T>>,
+    remaining: usize,
+}
+
+/// An enumerated owned iterator of `OptionVec<T>` elements, yielding `(usize, T)`.
+#[derive(Debug)]
+pub struct IntoEnumerate<T>(iter::Enumerate<vec::IntoIter<Option<T>>>);
+
+/// An enumerated owned iterator of `OptionVec<T>` elements of known length,
+/// yielding `(usize, T)`.
+#[derive(Debug)]
+pub struct KeyedIntoIter<T> {
+    iter: iter::Enumerate<vec::IntoIter<Option<T>>>,
+    remaining: usize,
+}
+
+/// A draining iterator of `OptionVec<T>` elements which leaves positions
+/// in place, yielding `(usize, T)`.
+#[derive(Debug)]
+pub struct DrainKeepLayout<'a, T: 'a> {
+    vec: &'a mut OptionVec<T>,
+    front: usize,
+    back: usize,
+}
+
+/// An enumerated iterator of borrowed `OptionVec<T>` elements, yielding `(usize, &T)`.
+#[derive(Debug)]
+pub struct Enumerate<'a, T: 'a>(Iter<'a, T>);
+
+/// An enumerated iterator of mutable `OptionVec<T>` elements, yielding `(usize, &mut T)`.
+#[derive(Debug)]
+pub struct EnumerateMut<'a, T: 'a>(iter::Enumerate<slice::IterMut<'a, Option<T>>>);
+
+/// Indices of elements kept and removed by `OptionVec::retain_report`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RetainReport {
+    /// Indices of elements which were retained, in ascending order.
+    pub kept: Vec<usize>,
+    /// Indices of elements which were removed, in ascending order.
+    pub removed: Vec<usize>,
+}
+
+/// Determines what `OptionVec::update_retain` does with an element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action<T> {
+    /// Keep the element.
+    Keep,
+    /// Remove the element, leaving `None` in its position.
+    Remove,
+    /// Replace the element with the given value.
+    Replace(T),
+}
+
+/// An error returned when an element is not present at a position.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GetError {
+    /// The index is beyond the end of the container.
+    OutOfBounds(usize),
+    /// The position at this index is `None`.
+    Empty(usize),
+}
+
+impl fmt::Display for GetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GetError::OutOfBounds(idx) => write!(f, "index {} is out of bounds", idx),
+            GetError::Empty(idx) => write!(f, "index {} is empty", idx),
+        }
+    }
+}
+
+impl Error for GetError {}
+
+/// An error returned when given ranges are not valid for an operation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RangeError {
+    /// A range is reversed or extends beyond the end of the container.
+    OutOfBounds,
+    /// Ranges are not of equal length.
+    LengthMismatch,
+    /// Ranges overlap.
+    Overlapping,
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            RangeError::OutOfBounds => "range out of bounds",
+            RangeError::LengthMismatch => "ranges differ in length",
+            RangeError::Overlapping => "ranges overlap",
+        })
+    }
+}
+
+impl Error for RangeError {}
+
+/// An error returned by `OptionVec::try_replace_range`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReplaceError {
+    /// The range is reversed or extends beyond the end of the container.
+    OutOfBounds,
+    /// The replacement is not of the same length as the range.
+    LengthMismatch,
+}
+
+impl fmt::Display for ReplaceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            ReplaceError::OutOfBounds => "range out of bounds",
+            ReplaceError::LengthMismatch => "replacement differs in length from range",
+        })
+    }
+}
+
+impl Error for ReplaceError {}
+
+/// An error returned by `OptionVec::shift_within`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ShiftError {
+    /// The range, or the destination of an element, is beyond the end of
+    /// the container.
+    OutOfBounds,
+    /// The destination index holds an element from outside the range.
+    Collision(usize),
+}
+
+impl fmt::Display for ShiftError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShiftError::OutOfBounds => f.write_str("shift out of bounds"),
+            ShiftError::Collision(idx) => write!(f, "element already exists at index {}", idx),
+        }
+    }
+}
+
+impl Error for ShiftError {}
+
+/// Determines how `OptionVec::collect_indexed` handles duplicate indices.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the first element given for an index.
+    KeepFirst,
+    /// Keep the last element given for an index.
+    KeepLast,
+    /// Return an error.
+    Error,
+}
+
+/// An error returned when more than one element is given the same index.
+///
+/// Returned by `OptionVec::map_indices` and `OptionVec::collect_indexed`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IndexMapError {
+    /// More than one element would be placed at this index.
+    Collision(usize),
+}
+
+impl fmt::Display for IndexMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IndexMapError::Collision(idx) => write!(f, "multiple elements mapped to index {}", idx),
+        }
+    }
+}
+
+impl Error for IndexMapError {}
+
+/// An error returned by `OptionVec::rehome`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RehomeError {
+    /// No new index was given for the element at this index.
+    Missing(usize),
+    /// More than one element would be moved to this index.
+    Collision(usize),
+}
+
+impl fmt::Display for RehomeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RehomeError::Missing(idx) => write!(f, "no mapping for index {}", idx),
+            RehomeError::Collision(idx) => write!(f, "multiple elements mapped to index {}", idx),
+        }
+    }
+}
+
+impl Error for RehomeError {}
+
+/// An error returned by `OptionVec::from_parts_with_free_list`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PartsError {
+    /// The free list contains an index beyond the end of the positions.
+    OutOfBounds(usize),
+    /// The free list contains this index more than once.
+    Duplicate(usize),
+    /// The free list contains the index of an element.
+    Occupied(usize),
+    /// The free list does not contain the index of this `None` position.
+    Missing(usize),
+}
+
+impl fmt::Display for PartsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PartsError::OutOfBounds(idx) => write!(f, "free index {} out of bounds", idx),
+            PartsError::Duplicate(idx) => write!(f, "free index {} listed more than once", idx),
+            PartsError::Occupied(idx) => write!(f, "free index {} holds an element", idx),
+            PartsError::Missing(idx) => write!(f, "vacant index {} not in free list", idx),
+        }
+    }
+}
+
+impl Error for PartsError {}
+
+/// An error returned by `OptionVec::validate`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The cached length differs from the number of elements.
+    Length {
+        /// Length reported by `len`
+        cached: usize,
+        /// Number of `Some` positions
+        actual: usize,
+    },
+    /// The `None` position at this index is not recorded as vacant.
+    MissingFree(usize),
+    /// The occupancy bitmap disagrees with the position at this index.
+    Bitmap(usize),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::Length{cached, actual} =>
+                write!(f, "cached length {} differs from element count {}", cached, actual),
+            ValidationError::MissingFree(idx) => write!(f, "vacant index {} not recorded", idx),
+            ValidationError::Bitmap(idx) => write!(f, "occupancy bit mismatch at index {}", idx),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// A run of slots in the run-length encoding of an `OptionVec<T>`.
+///
+/// Produced by `OptionVec::to_rle` and consumed by `OptionVec::from_rle`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RleSegment<T> {
+    /// A run of the given number of `None` positions.
+    Holes(usize),
+    /// A run of consecutive elements.
+    Values(Vec<T>),
+}
+
+/// A difference at a single position between two `OptionVec<T>` containers.
+///
+/// Produced by `OptionVec::diff`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SlotDiff<'a, T: 'a> {
+    /// An element is present in the target, but not in the source.
+    Added(usize, &'a T),
+    /// An element is present in the source, but not in the target.
+    Removed(usize),
+    /// Elements are present in both, but are not equal.
+    Changed(usize, &'a T),
+}
+
+/// A guard which dereferences to the internal `Vec<Option<T>>` of an
+/// `OptionVec<T>`.
+///
+/// Returned by `OptionVec::inner_mut`. Contained elements are recounted,
+/// and vacant and occupied positions are reindexed, when the guard is dropped.
+#[derive(Debug)]
+pub struct InnerMut<'a, T: 'a>(&'a mut OptionVec<T>);
+
+/// A mutable view of an `OptionVec<T>` which iterates as `(usize, &mut T)`.
+#[derive(Debug)]
+pub struct IndexedMutView<'a, T: 'a>(&'a mut OptionVec<T>);
+
+/// A view into a single position of an `OptionVec<T>`.
+///
+/// Returned by `OptionVec::entry`.
+#[derive(Debug)]
+pub enum Entry<'a, T: 'a> {
+    /// An occupied position.
+    Occupied(OccupiedEntry<'a, T>),
+    /// A vacant position.
+    Vacant(VacantEntry<'a, T>),
+}
+
+/// A view into an occupied position of an `OptionVec<T>`.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, T: 'a> {
+    vec: &'a mut OptionVec<T>,
+    idx: usize,
+}
+
+/// A view into a vacant position of an `OptionVec<T>`.
+#[derive(Debug)]
+pub struct VacantEntry<'a, T: 'a> {
+    vec: &'a mut OptionVec<T>,
+    idx: usize,
+}
+
+macro_rules! option_vec_iter {
+    ( $name:ident , $r:ty , $pat:pat , $v:expr ) => {
+        impl<'a, T: 'a> Iterator for $name<'a, T> {
+            type Item = $r;
+
+            fn next(&mut self) -> Option<$r> {
+                for v in self.0.by_ref() {
+                    if let $pat = v {
+                        return Some($v);
+                    }
+                }
+
+                None
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                let (_, max) = self.0.size_hint();
+                (0, max)
+            }
+        }
+
+        impl<'a, T: 'a> DoubleEndedIterator for $name<'a, T> {
+            fn next_back(&mut self) -> Option<$r> {
+                while let Some(v) = self.0.next_back() {
+                    if let $pat = v {
+                        return Some($v);
+                    }
+                }
+
+                None
+            }
+
+            fn rfold<B, F>(self, init: B, mut f: F) -> B
+                    where F: FnMut(B, $r) -> B {
+                self.0.rfold(init, |acc, v| match v {
+                    $pat => f(acc, $v),
+                    _ => acc
+                })
+            }
+        }
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let v = self.iter.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some(v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        while let Some(v) = self.iter.next_back() {
+            if let Some(v) = v {
+                self.remaining -= 1;
+                return Some(v);
+            }
+        }
+
+        None
+    }
+
+    fn rfold<B, F>(self, init: B, mut f: F) -> B
+            where F: FnMut(B, T) -> B {
+        self.iter.rfold(init, |acc, v| match v {
+            Some(v) => f(acc, v),
+            None => acc
+        })
+    }
+}
+
+impl<'a, T: 'a> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let end = self.front + self.iter.len();
+
+        while let Some(idx) = next_set_bit(self.bits, self.front, end) {
+            let v = self.iter.nth(idx - self.front);
+            self.front = idx + 1;
+            debug_assert!(matches!(v, Some(Some(_))), "no element at index {}", idx);
+
+            if let Some(Some(v)) = v {
+                self.last = Some(idx);
+                self.remaining -= 1;
+                return Some(v);
+            }
+        }
+
+        self.iter.nth(end - self.front);
+        self.front = end;
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining
+    }
+}
+
+impl<'a, T: 'a> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        let mut end = self.front + self.iter.len();
+
+        while let Some(idx) = prev_set_bit(self.bits, self.front, end) {
+            let v = self.iter.nth_back(end - 1 - idx);
+            end = idx;
+            debug_assert!(matches!(v, Some(Some(_))), "no element at index {}", idx);
+
+            if let Some(Some(v)) = v {
+                self.last = Some(idx);
+                self.remaining -= 1;
+                return Some(v);
+            }
+        }
+
+        self.iter.nth_back(end - self.front);
+        None
+    }
+
+    fn rfold<B, F>(self, init: B, mut f: F) -> B
+            where F: FnMut(B, &'a T) -> B {
+        self.iter.rfold(init, |acc, v| match v {
+            Some(v) => f(acc, v),
+            None => acc
+        })
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T: 'a> Iterator for Enumerate<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<(usize, &'a T)> {
+        let v = self.0.next()?;
+        self.0.last.map(|idx| (idx, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+impl<'a, T: 'a> DoubleEndedIterator for Enumerate<'a, T> {
+    fn next_back(&mut self) -> Option<(usize, &'a T)> {
+        let v = self.0.next_back()?;
+        self.0.last.map(|idx| (idx, v))
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for Enumerate<'a, T> {}
+
+impl<'a, T: 'a> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        let v = self.iter.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some(v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining
+    }
+}
+
+impl<'a, T: 'a> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        while let Some(v) = self.iter.next_back() {
+            if let Some(v) = v {
+                self.remaining -= 1;
+                return Some(v);
+            }
+        }
+
+        None
+    }
+
+    fn rfold<B, F>(self, init: B, mut f: F) -> B
+            where F: FnMut(B, &'a mut T) -> B {
+        self.iter.rfold(init, |acc, v| match v {
+            Some(v) => f(acc, v),
+            None => acc
+        })
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for IterMut<'a, T> {}
+
+impl<T> Iterator for IntoEnumerate<T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<(usize, T)> {
+        for (n, v) in self.0.by_ref() {
+            if let Some(v) = v {
+                return Some((n, v));
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, max) = self.0.size_hint();
+        (0, max)
+    }
+}
+
+impl<T> Iterator for KeyedIntoIter<T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<(usize, T)> {
+        for (n, v) in self.iter.by_ref() {
+            if let Some(v) = v {
+                self.remaining -= 1;
+                return Some((n, v));
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining
+    }
+}
+
+impl<T> DoubleEndedIterator for KeyedIntoIter<T> {
+    fn next_back(&mut self) -> Option<(usize, T)> {
+        while let Some((n, v)) = self.iter.next_back() {
+            if let Some(v) = v {
+                self.remaining -= 1;
+                return Some((n, v));
+            }
+        }
+
+        None
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> ExactSizeIterator for KeyedIntoIter<T> {}
+
+impl<'a, T: 'a> Iterator for DrainKeepLayout<'a, T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<(usize, T)> {
+        let idx = next_set_bit(&self.vec.bits, self.front, self.back);
+
+        self.front = idx.map_or(self.back, |idx| idx + 1);
+        idx.and_then(|idx| self.vec.remove(idx).map(|t| (idx, t)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.vec.len, Some(self.vec.len))
+    }
+}
+
+impl<'a, T: 'a> DoubleEndedIterator for DrainKeepLayout<'a, T> {
+    fn next_back(&mut self) -> Option<(usize, T)> {
+        let idx = prev_set_bit(&self.vec.bits, self.front, self.back);
+
+        self.back = idx.unwrap_or(self.front);
+        idx.and_then(|idx| self.vec.remove(idx).map(|t| (idx, t)))
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for DrainKeepLayout<'a, T> {}
+
+impl<'a, T: 'a> Drop for DrainKeepLayout<'a, T> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+option_vec_iter!{ EnumerateMut, (usize, &'a mut T), (n, Some(v)), (n, v) }
+
+impl<T> IntoIter<T> {
+    /// Returns the remaining slots of this iterator as a slice.
+    #[inline]
+    pub fn as_slice(&self) -> &[Option<T>] {
+        self.iter.as_slice()
+    }
+
+    /// Collects the remaining elements of this iterator into a new
+    /// `OptionVec<T>`, with no `None` positions.
+    #[inline]
+    pub fn into_option_vec(self) -> OptionVec<T> {
+        self.collect()
+    }
+}
+
+impl<'a, T: 'a> Iter<'a, T> {
+    /// Returns the remaining slots of this iterator as a slice.
+    #[inline]
+    pub fn as_slice(&self) -> &'a [Option<T>] {
+        self.iter.as_slice()
+    }
+
+    /// Returns the index of the element most recently yielded by this
+    /// iterator, from either end.
+    #[inline]
+    pub fn last_yielded_index(&self) -> Option<usize> {
+        self.last
+    }
+}
+
+impl<'a, T: 'a> IterMut<'a, T> {
+    /// Splits the remaining slots of this iterator into two iterators
+    /// at the midpoint.
+    ///
+    /// The first iterator covers the lower half of the remaining slots
+    /// and the second covers the upper half. Elements in the lower half
+    /// are counted, so this operation is `O(N)`.
+    pub fn split(self) -> (IterMut<'a, T>, IterMut<'a, T>) {
+        let slice = self.iter.into_slice();
+        let mid = slice.len() / 2;
+        let (a, b) = slice.split_at_mut(mid);
+        let n = a.iter().filter(|v| v.is_some()).count();
+
+        (IterMut{iter: a.iter_mut(), remaining: n},
+            IterMut{iter: b.iter_mut(), remaining: self.remaining - n})
+    }
+}
+
+impl<'a, T: 'a> Entry<'a, T> {
+    /// Returns the index of this entry.
+    #[inline]
+    pub fn key(&self) -> usize {
+        match *self {
+            Entry::Occupied(ref e) => e.key(),
+            Entry::Vacant(ref e) => e.key(),
+        }
+    }
+
+    /// Inserts `t` if the entry is vacant and returns a mutable reference
+    /// to the element.
+    #[inline]
+    pub fn or_insert(self, t: T) -> &'a mut T {
+        self.or_insert_with(|| t)
+    }
+
+    /// Inserts the result of `f` if the entry is vacant and returns
+    /// a mutable reference to the element.
+    #[inline]
+    pub fn or_insert_with<F>(self, f: F) -> &'a mut T
+            where F: FnOnce() -> T {
+        self.or_insert_with_key(|_| f())
+    }
+
+    /// Inserts the result of calling `f` with the entry's index if the entry
+    /// is vacant and returns a mutable reference to the element.
+    pub fn or_insert_with_key<F>(self, f: F) -> &'a mut T
+            where F: FnOnce(usize) -> T {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let t = f(e.key());
+                e.insert(t)
+            }
+        }
+    }
+}
+
+impl<'a, T: 'a> OccupiedEntry<'a, T> {
+    /// Returns the index of this entry.
+    #[inline]
+    pub fn key(&self) -> usize {
+        self.idx
+    }
+
+    /// Returns a reference to the element.
+    #[inline]
+    pub fn get(&self) -> &T {
+        &self.vec[self.idx]
+    }
+
+    /// Returns a mutable reference to the element.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.vec[self.idx]
+    }
+
+    /// Converts the entry into a mutable reference to the element.
+    #[inline]
+    pub fn into_mut(self) -> &'a mut T {
+        &mut self.vec[self.idx]
+    }
+
+    /// Replaces the element, returning the previous element.
+    #[inline]
+    pub fn insert(&mut self, t: T) -> T {
+        ::std::mem::replace(self.get_mut(), t)
+    }
+
+    /// Removes and returns the element.
+    #[inline]
+    pub fn remove(self) -> T {
+        self.vec.remove(self.idx).expect("occupied entry is empty")
+    }
+}
+
+impl<'a, T: 'a> VacantEntry<'a, T> {
+    /// Returns the index of this entry.
+    #[inline]
+    pub fn key(&self) -> usize {
+        self.idx
+    }
+
+    /// Inserts an element into the entry's position and returns a mutable
+    /// reference to it.
+    ///
+    /// The container is extended with `None` elements, as by `insert_at`,
+    /// if the position is beyond its end.
+    pub fn insert(self, t: T) -> &'a mut T {
+        self.vec.insert_at(self.idx, t);
+        &mut self.vec[self.idx]
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for IntoIter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("IntoIter")
+            .field(&self.iter.as_slice())
+            .finish()
+    }
+}
+
+impl<'a, T: 'a> Clone for Iter<'a, T> {
+    fn clone(&self) -> Iter<'a, T> {
+        Iter{
+            iter: self.iter.clone(),
+            bits: self.bits,
+            front: self.front,
+            last: self.last,
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, T: 'a + fmt::Debug> fmt::Debug for Iter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("IntoIter")
+            .field(&self.iter.as_slice())
+            .finish()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OptionVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.vec.iter()
+                .enumerate().filter(|&(_idx, v)| v.is_some()))
+            .finish()
+    }
+}
+
+impl<'a, T: 'a> ops::Deref for InnerMut<'a, T> {
+    type Target = Vec<Option<T>>;
+
+    #[inline]
+    fn deref(&self) -> &Vec<Option<T>> {
+        &self.0.vec
+    }
+}
+
+impl<'a, T: 'a> ops::DerefMut for InnerMut<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Vec<Option<T>> {
+        &mut self.0.vec
+    }
+}
+
+impl<'a, T: 'a> Drop for InnerMut<'a, T> {
+    fn drop(&mut self) {
+        self.0.recount();
+        self.0.reindex();
+    }
+}
+
+impl<'a, T: 'a> Clone for Enumerate<'a, T> {
+    fn clone(&self) -> Enumerate<'a, T> {
         Enumerate(self.0.clone())
     }
-}
+}
+
+impl<T: Clone> Clone for OptionVec<T> {
+    fn clone(&self) -> OptionVec<T> {
+        let end = self.end_occupied();
+
+        OptionVec{
+            vec: self.vec[..end].to_vec(),
+            len: self.len,
+            free: self.free.clone(),
+            bits: self.bits.clone(),
+            growth_guard: self.growth_guard,
+            exact_growth: self.exact_growth,
+        }
+    }
+
+    fn clone_from(&mut self, other: &OptionVec<T>) {
+        let end = other.end_occupied();
+
+        self.len = other.len;
+        self.free.clone_from(&other.free);
+        self.bits.clone_from(&other.bits);
+        self.growth_guard = other.growth_guard;
+        self.exact_growth = other.exact_growth;
+        self.vec.truncate(end);
+        let len = self.vec.len();
+
+        self.vec.clone_from_slice(&other.vec[..len]);
+        self.vec.extend_from_slice(&other.vec[len..end]);
+    }
+}
+
+impl<T> Default for OptionVec<T> {
+    fn default() -> OptionVec<T> {
+        OptionVec::new()
+    }
+}
+
+impl<T> From<Vec<Option<T>>> for OptionVec<T> {
+    fn from(v: Vec<Option<T>>) -> OptionVec<T> {
+        let mut v = OptionVec{
+            vec: v,
+            len: 0,
+            free: BinaryHeap::new(),
+            bits: Vec::new(),
+            growth_guard: None,
+            exact_growth: false,
+        };
+
+        v.recount();
+        v.reindex();
+        v
+    }
+}
+
+impl<T> From<BTreeMap<usize, T>> for OptionVec<T> {
+    fn from(map: BTreeMap<usize, T>) -> OptionVec<T> {
+        let end = map.keys().next_back().map_or(0, |&n| n + 1);
+
+        let mut vec = Vec::with_capacity(end);
+        vec.resize_with(end, || None);
+
+        for (idx, t) in map {
+            vec[idx] = Some(t);
+        }
+
+        OptionVec::from(vec)
+    }
+}
+
+impl<T> From<OptionVec<T>> for Vec<Option<T>> {
+    fn from(v: OptionVec<T>) -> Vec<Option<T>> {
+        v.vec
+    }
+}
+
+impl<T> Extend<T> for OptionVec<T> {
+    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=T> {
+        let iter = iter.into_iter();
+
+        let (low, _) = iter.size_hint();
+        self.reserve(low);
+
+        for v in iter {
+            self.insert(v);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for OptionVec<T> {
+    fn from_iter<I>(iter: I) -> OptionVec<T> where I: IntoIterator<Item=T> {
+        OptionVec::from(iter.into_iter().map(Some).collect::<Vec<_>>())
+    }
+}
+
+macro_rules! impl_eq {
+    ( $rhs:ty ) => {
+        impl<'b, A, B> PartialEq<$rhs> for OptionVec<A> where A: PartialEq<B> {
+            #[inline]
+            fn eq(&self, rhs: &$rhs) -> bool { self.iter().eq(rhs.iter()) }
+        }
+    }
+}
+
+impl_eq!{ OptionVec<B> }
+impl_eq!{ Vec<B> }
+impl_eq!{ &'b [B] }
+
+impl<T> Eq for OptionVec<T> where T: Eq {}
+
+impl<T> PartialOrd for OptionVec<T> where T: PartialOrd {
+    #[inline]
+    fn partial_cmp(&self, rhs: &OptionVec<T>) -> Option<Ordering> {
+        self.iter().partial_cmp(rhs.iter())
+    }
+
+    #[inline]
+    fn lt(&self, rhs: &OptionVec<T>) -> bool { self.iter().lt(rhs.iter()) }
+    #[inline]
+    fn le(&self, rhs: &OptionVec<T>) -> bool { self.iter().le(rhs.iter()) }
+    #[inline]
+    fn gt(&self, rhs: &OptionVec<T>) -> bool { self.iter().gt(rhs.iter()) }
+    #[inline]
+    fn ge(&self, rhs: &OptionVec<T>) -> bool { self.iter().ge(rhs.iter()) }
+}
+
+impl<T> Ord for OptionVec<T> where T: Ord {
+    #[inline]
+    fn cmp(&self, rhs: &OptionVec<T>) -> Ordering {
+        self.iter().cmp(rhs.iter())
+    }
+}
+
+impl<T> ops::Index<usize> for OptionVec<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, idx: usize) -> &T {
+        self.vec[idx].as_ref().unwrap_or_else(|| panic!("index {} is empty", idx))
+    }
+}
+
+impl<T> ops::IndexMut<usize> for OptionVec<T> {
+    #[inline]
+    fn index_mut(&mut self, idx: usize) -> &mut T {
+        self.vec[idx].as_mut().unwrap_or_else(|| panic!("index {} is empty", idx))
+    }
+}
+
+impl<T> IntoIterator for OptionVec<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    #[inline]
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter{
+            iter: self.vec.into_iter(),
+            remaining: self.len,
+        }
+    }
+}
+
+impl<T> IntoIterator for Box<OptionVec<T>> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    #[inline]
+    fn into_iter(self) -> IntoIter<T> {
+        (*self).into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a OptionVec<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut OptionVec<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<'a, T> IntoIterator for IndexedMutView<'a, T> {
+    type Item = (usize, &'a mut T);
+    type IntoIter = EnumerateMut<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> EnumerateMut<'a, T> {
+        self.0.enumerate_mut()
+    }
+}
+
+/// A key identifying an element of a `GenOptionVec<T>`.
+///
+/// A key refers only to the element for which it was returned. Once that
+/// element is removed, the key is stale, even if another element is later
+/// inserted at the same index.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Key {
+    /// Position of the element.
+    pub index: usize,
+    /// Number of times an element at this position had been removed
+    /// when the element was inserted.
+    pub generation: u64,
+}
+
+/// An `OptionVec<T>` which detects the use of stale indices.
+///
+/// Inserting an element returns a `Key`, rather than an index. Each position
+/// has a generation which is incremented when its element is removed, so a
+/// `Key` for a removed element is rejected by `get` and `remove` even when
+/// a new element occupies its position.
+///
+/// Like `OptionVec<T>`, an element inserted into a `GenOptionVec<T>` will
+/// occupy the first available position.
+#[derive(Clone, Debug)]
+pub struct GenOptionVec<T> {
+    vec: OptionVec<T>,
+    gens: Vec<u64>,
+}
+
+impl<T> GenOptionVec<T> {
+    /// Creates an empty `GenOptionVec<T>`.
+    #[inline]
+    pub fn new() -> GenOptionVec<T> {
+        GenOptionVec::with_capacity(0)
+    }
+
+    /// Creates an empty `GenOptionVec<T>` with capacity for `n` elements.
+    #[inline]
+    pub fn with_capacity(n: usize) -> GenOptionVec<T> {
+        GenOptionVec{
+            vec: OptionVec::with_capacity(n),
+            gens: Vec::with_capacity(n),
+        }
+    }
+
+    /// Returns the number of contained elements.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// Returns whether the container is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    /// Inserts an element into the first available position, returning
+    /// its key.
+    pub fn insert(&mut self, t: T) -> Key {
+        let index = self.vec.insert(t);
+
+        if index >= self.gens.len() {
+            self.gens.resize(index + 1, 0);
+        }
+
+        Key{index, generation: self.gens[index]}
+    }
+
+    /// Returns whether the element for the given key is present.
+    #[inline]
+    pub fn contains(&self, key: Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the element for the given key, if it has not been removed.
+    pub fn get(&self, key: Key) -> Option<&T> {
+        if self.is_current(key) {
+            self.vec.get(key.index)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the element for the given key,
+    /// if it has not been removed.
+    pub fn get_mut(&mut self, key: Key) -> Option<&mut T> {
+        if self.is_current(key) {
+            self.vec.get_mut(key.index)
+        } else {
+            None
+        }
+    }
+
+    /// Removes and returns the element for the given key, if it has not
+    /// already been removed.
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        if !self.is_current(key) {
+            return None;
+        }
+
+        let t = self.vec.remove(key.index)?;
+        self.gens[key.index] = self.gens[key.index].wrapping_add(1);
+        Some(t)
+    }
+
+    /// Removes all contained elements, invalidating their keys.
+    pub fn clear(&mut self) {
+        for (i, _) in self.vec.enumerate() {
+            self.gens[i] = self.gens[i].wrapping_add(1);
+        }
+
+        self.vec.clear();
+    }
+
+    /// Returns an iterator over references with their keys.
+    ///
+    /// The elements yielded by this iterator will be `(Key, &T)`.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item=(Key, &T)> {
+        self.vec.enumerate()
+            .map(move |(index, t)| (Key{index, generation: self.gens[index]}, t))
+    }
+
+    fn is_current(&self, key: Key) -> bool {
+        self.gens.get(key.index) == Some(&key.generation)
+    }
+}
+
+impl<T> Default for GenOptionVec<T> {
+    fn default() -> GenOptionVec<T> {
+        GenOptionVec::new()
+    }
+}
+
+impl<T> ops::Index<Key> for GenOptionVec<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, key: Key) -> &T {
+        self.get(key).unwrap_or_else(|| panic!("key {:?} is stale", key))
+    }
+}
+
+impl<T> ops::IndexMut<Key> for GenOptionVec<T> {
+    #[inline]
+    fn index_mut(&mut self, key: Key) -> &mut T {
+        self.get_mut(key).unwrap_or_else(|| panic!("key {:?} is stale", key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OptionVec;
+
+    #[test]
+    fn test_len() {
+        let v = OptionVec::from(vec![
+            None, Some("foo"), None, Some("bar"), None]);
+
+        assert_eq!(v.len(), 2);
+    }
+
+    #[test]
+    fn test_validate() {
+        use super::ValidationError;
+
+        let mut v = OptionVec::from(vec![None, Some(1), None, Some(2)]);
+        assert_eq!(v.validate(), Ok(()));
+
+        for i in 0..100 {
+            v.insert_at(i * 3, i);
+        }
+        v.remove(42);
+        v.retain(|x| *x % 4 != 0);
+        v.resize_filled(150, 0);
+        assert_eq!(v.validate(), Ok(()));
+
+        let mut w = v.clone();
+        w.len += 1;
+        assert_eq!(w.validate(), Err(ValidationError::Length{
+            cached: v.len() + 1, actual: v.len()}));
+
+        let mut w = v.clone();
+        w.free.clear();
+        assert_eq!(w.validate(), Err(ValidationError::MissingFree(0)));
+
+        let mut w = v.clone();
+        w.bits[1] &= !(1 << 2);
+        assert_eq!(w.validate(), Err(ValidationError::Bitmap(66)));
+
+        let mut w = v.clone();
+        w.bits.push(1);
+        let idx = (w.bits.len() - 1) * 64;
+        assert_eq!(w.validate(), Err(ValidationError::Bitmap(idx)));
+
+        let mut w = v.clone();
+        w.bits.clear();
+        assert_eq!(w.validate(), Err(ValidationError::Bitmap(1)));
+    }
+
+    #[test]
+    fn test_from_parts_with_free_list() {
+        use super::PartsError;
+
+        let v = OptionVec::from_parts_with_free_list(
+            vec![None, Some(1), None, Some(2)], vec![2, 0]).unwrap();
+
+        assert_eq!(v.len(), 2);
+        assert_eq!(v.validate(), Ok(()));
+        assert_eq!(v.iter().collect::<Vec<_>>(), [&1, &2]);
+
+        let mut v = v;
+        assert_eq!(v.insert(3), 0);
+        assert_eq!(v.insert(4), 2);
+        assert_eq!(v.insert(5), 4);
+
+        let parts = || vec![None, Some(1), None];
+
+        assert_eq!(OptionVec::from_parts_with_free_list(parts(), vec![0, 1, 2]).err(),
+            Some(PartsError::Occupied(1)));
+        assert_eq!(OptionVec::from_parts_with_free_list(parts(), vec![0, 3]).err(),
+            Some(PartsError::OutOfBounds(3)));
+        assert_eq!(OptionVec::from_parts_with_free_list(parts(), vec![2, 0, 2]).err(),
+            Some(PartsError::Duplicate(2)));
+        assert_eq!(OptionVec::from_parts_with_free_list(parts(), vec![2]).err(),
+            Some(PartsError::Missing(0)));
+    }
+
+    #[test]
+    fn test_inner_mut() {
+        let mut v = OptionVec::from(vec![Some(1), None, Some(2)]);
+
+        {
+            let mut slots = v.inner_mut();
+            slots[1] = Some(3);
+            slots[0] = None;
+            slots.extend(vec![None, Some(4), None]);
+        }
+
+        assert_eq!(v.validate(), Ok(()));
+        assert_eq!(v.len(), 3);
+        assert_eq!(v.iter().collect::<Vec<_>>(), [&3, &2, &4]);
+        assert_eq!(v.insert(5), 0);
+        assert_eq!(v.insert(6), 3);
+        assert_eq!(v.insert(7), 5);
+        assert_eq!(v.insert(8), 6);
+    }
+
+    #[test]
+    fn test_slots() {
+        let v = OptionVec::from(vec![
+            Some(1), None, Some(2)]);
+
+        assert_eq!(v.slots()[0], Some(1));
+        assert_eq!(&v.slots()[1], &None);
+        assert_eq!(v.slots().len(), 3);
+    }
+
+    #[test]
+    fn test_occupied_len() {
+        let v = OptionVec::from(vec![
+            None, Some(1), None, Some(2)]);
+
+        assert_eq!(v.occupied_len(), 2);
+        assert_eq!(v.occupied_len(), v.len());
+        assert_eq!(v.slot_len(), 4);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), None]);
+
+        let snap = v.snapshot();
+        let shared = snap.clone();
+
+        v.insert(3);
+        v.remove(0);
+
+        assert_eq!(&snap[..], &[Some(1), None, Some(2)]);
+        assert_eq!(&shared[..], &snap[..]);
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2)]);
+
+        let snap = v.snapshot();
+
+        v.insert(3);
+        v.insert_at(5, 4);
+        v.remove(0);
+
+        v.restore(&snap);
+
+        assert_eq!(v.slots(), &snap[..]);
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut v = OptionVec::from(vec![
+            Some(()), None, Some(())]);
+
+        assert_eq!(v.len(), 2);
+
+        assert_eq!(v.insert(()), 1);
+        assert_eq!(v.len(), 3);
+
+        assert_eq!(v.insert(()), 3);
+        assert_eq!(v.len(), 4);
+    }
+
+    #[test]
+    fn test_insert_at() {
+        let mut v = OptionVec::from(vec![Some(1)]);
+
+        assert_eq!(v.insert_at(0, 2), Some(1));
+        assert_eq!(v.insert_at(3, 3), None);
+
+        assert_eq!(v.inner(), &vec![Some(2), None, None, Some(3)]);
+    }
+
+    #[test]
+    fn test_growth_guard() {
+        let mut v = OptionVec::from(vec![Some(1), Some(2)]);
+
+        v.set_growth_guard(Some(4));
+        v.insert_at(7, 3);
+        assert_eq!(v.slot_len(), 8);
+
+        v.set_growth_guard(None);
+        v.insert_at(1000, 4);
+        assert_eq!(v.slot_len(), 1001);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds guard")]
+    fn test_growth_guard_panic() {
+        let mut v = OptionVec::from(vec![Some(1), Some(2)]);
+
+        v.set_growth_guard(Some(4));
+        v.insert_at(1000, 3);
+    }
+
+    #[test]
+    fn test_from_fn_sparse() {
+        let v = OptionVec::from_fn_sparse(5, |i| if i % 2 == 0 { Some(i) } else { None });
+
+        assert_eq!(v.inner(), &vec![Some(0), None, Some(2), None, Some(4)]);
+    }
+
+    #[test]
+    fn test_from_sorted_pairs() {
+        let v = OptionVec::from_sorted_pairs(vec![(1, "a"), (2, "b"), (5, "c")]);
+
+        let w = OptionVec::new()
+            .with_value_at(1, "a")
+            .with_value_at(2, "b")
+            .with_value_at(5, "c");
+
+        assert_eq!(v.inner(), w.inner());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "ascending order")]
+    fn test_from_sorted_pairs_unsorted() {
+        let _ = OptionVec::from_sorted_pairs(vec![(2, "a"), (1, "b")]);
+    }
+
+    #[test]
+    fn test_try_into_dense() {
+        let v = OptionVec::from(vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(v.try_into_dense().ok(), Some(vec![1, 2, 3]));
+
+        let v = OptionVec::from(vec![Some(1), None, Some(3)]);
+        let v = v.try_into_dense().unwrap_err();
+        assert_eq!(v.inner(), &vec![Some(1), None, Some(3)]);
+    }
+
+    #[test]
+    fn test_unzip() {
+        let v = OptionVec::from(vec![
+            None, Some("a"), Some("b"), None, Some("c")]);
+
+        let (indices, values) = v.unzip();
+
+        assert_eq!(indices, vec![1, 2, 4]);
+        assert_eq!(values, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_split_dense_prefix() {
+        let mut v = OptionVec::from(vec![
+            Some(1), Some(2), Some(3), None, Some(5), None]);
+
+        assert_eq!(v.split_dense_prefix(), vec![1, 2, 3]);
+        assert_eq!(v.inner(), &vec![None, Some(5), None]);
+
+        assert_eq!(v.split_dense_prefix(), vec![]);
+    }
+
+    #[test]
+    fn test_collect_indexed() {
+        use super::{ConflictPolicy, IndexMapError};
+
+        let pairs = vec![(2, "a"), (0, "b"), (2, "c")];
+
+        let v = OptionVec::collect_indexed(pairs.clone(), ConflictPolicy::KeepFirst).unwrap();
+        assert_eq!(v.inner(), &vec![Some("b"), None, Some("a")]);
+
+        let v = OptionVec::collect_indexed(pairs.clone(), ConflictPolicy::KeepLast).unwrap();
+        assert_eq!(v.inner(), &vec![Some("b"), None, Some("c")]);
+
+        assert_eq!(OptionVec::collect_indexed(pairs, ConflictPolicy::Error).map(|_| ()),
+            Err(IndexMapError::Collision(2)));
+    }
+
+    #[test]
+    fn test_rle() {
+        use super::RleSegment;
+
+        let mut slots = vec![None; 100];
+        slots[10] = Some(1);
+        slots[11] = Some(2);
+        slots[50] = Some(3);
+        let v = OptionVec::from(slots);
+
+        let rle = v.to_rle();
+
+        assert_eq!(rle, vec![
+            RleSegment::Holes(10),
+            RleSegment::Values(vec![1, 2]),
+            RleSegment::Holes(38),
+            RleSegment::Values(vec![3]),
+            RleSegment::Holes(49),
+        ]);
+
+        assert_eq!(OptionVec::from_rle(rle).inner(), v.inner());
+    }
+
+    #[test]
+    fn test_grow_exact() {
+        let mut v = OptionVec::new();
+
+        v.grow_exact(true);
+        v.insert_at(4, 1);
+        assert_eq!(v.capacity(), 5);
+
+        v.insert_at(6, 2);
+        assert_eq!(v.capacity(), 7);
+    }
+
+    #[test]
+    fn test_with_value_at() {
+        let v = OptionVec::new()
+            .with_value_at(3, "x")
+            .with_value_at(0, "y");
+
+        assert_eq!(v.len(), 2);
+        assert_eq!(v.get(0), Some(&"y"));
+        assert_eq!(v.get(1), None);
+        assert_eq!(v.get(3), Some(&"x"));
+    }
+
+    #[test]
+    fn test_allocate_contiguous() {
+        let mut v = OptionVec::from(vec![
+            Some(0), None, Some(2), None, None, Some(5), None]);
+
+        assert_eq!(v.allocate_contiguous(2, |i| i * 10), 3);
+        assert_eq!(v.inner(), &vec![
+            Some(0), None, Some(2), Some(30), Some(40), Some(5), None]);
+
+        assert_eq!(v.allocate_contiguous(3, |i| i * 10), 6);
+        assert_eq!(v.inner()[6..], [Some(60), Some(70), Some(80)]);
+
+        assert_eq!(v.allocate_contiguous(1, |i| i * 10), 1);
+    }
+
+    #[test]
+    fn test_free_contiguous() {
+        let mut v = OptionVec::from(vec![Some(0), None]);
+
+        let start = v.allocate_contiguous(3, |i| i);
+        assert_eq!(start, 1);
+
+        assert_eq!(v.free_contiguous(start, 3), vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(v.inner(), &vec![Some(0), None, None, None]);
+
+        assert_eq!(v.allocate_contiguous(3, |i| i * 10), start);
+        assert_eq!(v.free_contiguous(3, 2), vec![Some(30)]);
+        assert_eq!(v.free_contiguous(1, usize::MAX), vec![Some(10), Some(20), None]);
+        assert_eq!(v.free_contiguous(usize::MAX, 2), vec![]);
+        assert_eq!(v.inner(), &vec![Some(0), None, None, None]);
+    }
+
+    #[test]
+    fn test_replace_if() {
+        let mut v = OptionVec::from(vec![Some(1), None]);
+
+        assert_eq!(v.replace_if(0, |&n| n == 1, 2), Ok(Some(1)));
+        assert_eq!(v.replace_if(0, |&n| n == 1, 3), Err(3));
+        assert_eq!(v.replace_if(1, |_| true, 4), Err(4));
+        assert_eq!(v.replace_if(2, |_| true, 5), Err(5));
+
+        assert_eq!(v.inner(), &vec![Some(2), None]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut v = OptionVec::from(vec![
+            Some(1), Some(2), Some(3)]);
+
+        assert_eq!(v.remove(0), Some(1));
+        assert_eq!(v.remove(0), None);
+    }
+
+    #[test]
+    fn test_resize_filled() {
+        let mut v = OptionVec::from(vec![Some(1), None]);
+
+        v.resize_filled(4, 7);
+        assert_eq!(v.inner(), &vec![Some(1), None, Some(7), Some(7)]);
+
+        v.resize_filled(1, 7);
+        assert_eq!(v.inner(), &vec![Some(1)]);
+    }
+
+    #[test]
+    fn test_resize_with_default() {
+        let mut v = OptionVec::from(vec![None, Some(1)]);
+
+        v.resize_with_default(3);
+        assert_eq!(v.inner(), &vec![None, Some(1), Some(0)]);
+        assert_eq!(v.len(), 2);
+
+        v.resize_with_default(1);
+        assert_eq!(v.inner(), &vec![None]);
+    }
+
+    #[test]
+    fn test_extend_exact() {
+        let mut v = OptionVec::new();
+
+        v.extend_exact(vec![1, 2, 3, 4, 5]);
+        assert!(v.capacity() >= 5);
+
+        let mut v = OptionVec::from(vec![None, Some(1), None]);
+
+        v.extend_exact(vec![2, 3, 4, 5]);
+        let cap = v.capacity();
+        assert!(cap >= 5);
+        assert_eq!(v.inner(), &vec![Some(2), Some(1), Some(3), Some(4), Some(5)]);
+
+        v.remove(0);
+        v.remove(3);
+        v.extend_exact(vec![6, 7]);
+        assert_eq!(v.capacity(), cap);
+        assert_eq!(v.inner(), &vec![Some(6), Some(1), Some(3), Some(7), Some(5)]);
+
+        let mut v = OptionVec::from(vec![None, Some(1), None]);
+        v.reserve_exact(10);
+        let cap = v.capacity();
+
+        v.extend_exact(0..cap - 1);
+        assert_eq!(v.capacity(), cap);
+        assert_eq!(v.slot_len(), cap);
+    }
+
+    #[test]
+    fn test_try_insert_many() {
+        let mut v = OptionVec::from(vec![Some(1), None]);
+
+        assert_eq!(v.try_insert_many(vec![2, 3]).unwrap(), vec![1, 2]);
+        assert_eq!(v.inner(), &vec![Some(1), Some(2), Some(3)]);
+
+        let (_, mut rest) = v.try_insert_many(::std::iter::repeat(4)).unwrap_err();
+
+        assert_eq!(rest.next(), Some(4));
+        assert_eq!(v.inner(), &vec![Some(1), Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn test_reserve_for_index() {
+        let mut v = OptionVec::from(vec![Some(1), None]);
+
+        v.reserve_for_index(99);
+
+        assert!(v.capacity() >= 100);
+        assert_eq!(v.slot_len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn test_reserve_for_index_overflow() {
+        let mut v = OptionVec::<u8>::new();
+
+        v.reserve_for_index(usize::MAX);
+    }
+
+    #[test]
+    fn test_reserve_for_density() {
+        let mut v = OptionVec::from(vec![Some(1), None]);
+
+        v.reserve_for_density(10, 0.3);
+
+        let cap = v.capacity();
+        assert!(cap >= 34);
+        assert_eq!(v.slot_len(), 2);
+
+        for i in (3..34).step_by(3) {
+            v.insert_at(i, i);
+        }
+        assert_eq!(v.slot_len(), 34);
+        assert_eq!(v.capacity(), cap);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut v = OptionVec::from(vec![
+            Some(1), Some(2), Some(3)]);
+
+        v.retain(|n| *n >= 2);
+
+        assert_eq!(v.len(), 2);
+    }
+
+    #[test]
+    fn test_retain_bounded() {
+        let mut v = OptionVec::from(vec![
+            Some(1), Some(2), Some(3), Some(4)]);
+
+        v.retain_bounded(|n| *n != 4, 0.5);
+        assert_eq!(v.slot_len(), 4);
+        assert_eq!(v.capacity(), 4);
+
+        v.retain_bounded(|n| *n == 1, 0.5);
+        assert_eq!(v.slot_len(), 1);
+        assert_eq!(v.capacity(), 1);
+    }
+
+    #[test]
+    fn test_retain_returning_slot_len() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), Some(3), Some(4), None]);
+
+        let n = v.retain_returning_slot_len(|n| *n < 3);
+
+        assert_eq!(n, 3);
+        assert_eq!(n, v.slot_len());
+    }
+
+    #[test]
+    fn test_try_retain() {
+        use std::ops::ControlFlow;
+
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), Some(3), Some(4), Some(5)]);
+        let mut budget = 3;
+
+        let res = v.try_retain(|n| {
+            if budget == 0 {
+                return ControlFlow::Break(*n);
+            }
+            budget -= 1;
+            ControlFlow::Continue(*n % 2 == 0)
+        });
+
+        assert_eq!(res, Some(4));
+        assert_eq!(v.inner(), &vec![None, None, Some(2), None, Some(4), Some(5)]);
+
+        assert_eq!(v.try_retain(|_| ControlFlow::<(), _>::Continue(true)), None);
+    }
+
+    #[test]
+    fn test_retain_rev() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), Some(3)]);
+        let mut seen = Vec::new();
+
+        v.retain_rev(|n| {
+            seen.push(*n);
+            *n != 2
+        });
+
+        assert_eq!(seen, vec![3, 2, 1]);
+        assert_eq!(v.inner(), &vec![Some(1), None, None, Some(3)]);
+    }
+
+    #[test]
+    fn test_retain_keys() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), Some(3), Some(4)]);
+        let mut seen = Vec::new();
+
+        v.retain_keys(|i| {
+            seen.push(i);
+            i < 3
+        });
+
+        assert_eq!(seen, vec![0, 2, 3, 4]);
+        assert_eq!(v.inner(), &vec![Some(1), None, Some(2), None, None]);
+    }
+
+    #[test]
+    fn test_retain_tombstoned() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), Some(3), Some(4)]);
+        let mut removed = Vec::new();
+
+        v.retain_tombstoned(|n| *n % 2 == 0, |i, n| removed.push((i, n)));
+
+        assert_eq!(removed, vec![(0, 1), (3, 3)]);
+        assert_eq!(v.inner(), &vec![None, None, Some(2), None, Some(4)]);
+    }
+
+    #[test]
+    fn test_remove_where() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), Some(3), Some(4)]);
+
+        assert_eq!(v.remove_where(|n| *n > 2), vec![(3, 3), (4, 4)]);
+        assert_eq!(v.inner(), &vec![Some(1), None, Some(2), None, None]);
+    }
+
+    #[test]
+    fn test_retain_report() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), Some(3), None, Some(4)]);
+
+        let report = v.retain_report(|n| *n % 2 == 0);
+
+        assert_eq!(report.kept, vec![2, 5]);
+        assert_eq!(report.removed, vec![0, 3]);
+        assert_eq!(v.inner(), &vec![None, None, Some(2), None, None, Some(4)]);
+    }
+
+    #[test]
+    fn test_retain_trim() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), Some(3), None]);
+        let cap = v.capacity();
+
+        v.retain_trim(|n| *n < 2);
+
+        assert_eq!(v.slot_len(), 1);
+        assert_eq!(v.capacity(), cap);
+    }
+
+    #[test]
+    fn test_retain_map() {
+        let mut v = OptionVec::from(vec![
+            Some(1), Some(2), None, Some(3), Some(4)]);
+
+        v.retain_map(|n| if n % 2 == 0 { Some(n * 2) } else { None });
+
+        assert_eq!(v.inner(), &vec![None, Some(4), None, None, Some(8)]);
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_par_retain() {
+        let mut v = (0..1000).collect::<OptionVec<_>>();
+        v.retain_keys(|i| i % 7 != 0);
+
+        let mut w = v.clone();
+
+        v.retain(|n| { *n += 1; *n % 3 != 0 });
+        w.par_retain(|n| { *n += 1; *n % 3 != 0 });
+
+        assert_eq!(w.inner(), v.inner());
+        assert_eq!(w.len(), v.len());
+        assert_eq!(w.validate(), Ok(()));
+        assert_eq!(w.insert(0), v.insert(0));
+    }
+
+    #[test]
+    fn test_retain_panic() {
+        use std::ops::ControlFlow;
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use super::Action;
+
+        let new = || OptionVec::from(vec![Some(1), Some(2), None, Some(3), Some(4)]);
+
+        let mut v = new();
+        assert!(catch_unwind(AssertUnwindSafe(|| v.retain(|&mut n| {
+            assert!(n < 3);
+            n == 2
+        }))).is_err());
+        assert_eq!(v.validate(), Ok(()));
+        assert_eq!(v.inner(), &vec![None, Some(2), None, Some(3), Some(4)]);
+        assert_eq!(v.pop_front(), Some(2));
+
+        let mut v = new();
+        assert!(catch_unwind(AssertUnwindSafe(|| v.try_retain(|&mut n| {
+            assert!(n < 3);
+            ControlFlow::Continue::<(), _>(n == 2)
+        }))).is_err());
+        assert_eq!(v.validate(), Ok(()));
+        assert_eq!(v.len(), 3);
+
+        let mut v = new();
+        assert!(catch_unwind(AssertUnwindSafe(|| v.retain_map(|n| {
+            assert!(n < 3);
+            Some(n)
+        }))).is_err());
+        assert_eq!(v.validate(), Ok(()));
+        assert_eq!(v.iter().collect::<Vec<_>>(), [&1, &2, &4]);
+
+        let mut v = new();
+        assert!(catch_unwind(AssertUnwindSafe(|| v.update_retain(|_, &mut n| {
+            assert!(n < 3);
+            Action::Remove
+        }))).is_err());
+        assert_eq!(v.validate(), Ok(()));
+        assert_eq!(v.len(), 2);
+
+        let mut v = new();
+        assert!(catch_unwind(AssertUnwindSafe(|| v.compact_by(|_, _| panic!()))).is_err());
+        assert_eq!(v.validate(), Ok(()));
+        assert_eq!(v.len(), v.slots().len());
+
+        let mut v = new();
+        assert!(catch_unwind(AssertUnwindSafe(|| v.sort_slots_by(|_, _| panic!()))).is_err());
+        assert_eq!(v.validate(), Ok(()));
+        assert_eq!(v.len(), 4);
+
+        let mut v = new();
+        assert!(catch_unwind(AssertUnwindSafe(|| v.compact_with(|_, _| panic!()))).is_err());
+        assert_eq!(v.validate(), Ok(()));
+        assert_eq!(v.len(), 4);
+    }
+
+    #[test]
+    fn test_clone() {
+        let a = OptionVec::from(vec![
+            Some(1), None, Some(2), None]);
+
+        let b = a.clone();
+
+        let mut c = OptionVec::new();
+        c.clone_from(&a);
+
+        let mut d = OptionVec::from(vec![Some(0); 10]);
+        d.clone_from(&a);
+
+        assert_eq!(a.len(), 2);
+        assert_eq!(b.len(), 2);
+        assert_eq!(c.len(), 2);
+        assert_eq!(d.len(), 2);
+
+        assert_eq!(a.inner().len(), 4);
+        assert_eq!(b.inner().len(), 3);
+        assert_eq!(c.inner().len(), 3);
+        assert_eq!(d.inner().len(), 3);
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), None, None]);
+
+        assert_eq!(v.len(), 2);
+        assert_eq!(v.inner().len(), 5);
+
+        v.shrink_to_fit();
+
+        assert_eq!(v.len(), 2);
+        assert_eq!(v.inner().len(), 3);
+    }
+
+    #[test]
+    fn test_compact_by_key() {
+        let mut v = OptionVec::from(vec![
+            Some((3, "c")), None, Some((1, "a")), Some((2, "b")), None]);
+
+        let moves = v.compact_by_key(|&(id, _)| id);
+
+        assert_eq!(v.iter().map(|&(_, s)| s).collect::<Vec<_>>(), vec!["a", "b", "c"]);
+        assert_eq!(moves, vec![(2, 0), (3, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn test_sort_slots_by() {
+        let mut v = OptionVec::from(vec![
+            None, Some(3), None, Some(1), Some(2)]);
+
+        v.sort_slots_by(|a, b| b.is_some().cmp(&a.is_some()));
+
+        assert_eq!(v.inner(), &vec![Some(3), Some(1), Some(2), None, None]);
+    }
+
+    #[test]
+    fn test_shrink_free_list() {
+        let mut v = (0..1000).collect::<OptionVec<_>>();
+
+        for _ in 0..3 {
+            for i in 0..1000 {
+                v.remove(i);
+            }
+            for i in 0..1000 {
+                v.insert_at(i, i);
+            }
+        }
+        for i in (0..1000).step_by(100) {
+            v.remove(i);
+        }
+
+        assert!(v.free.capacity() >= 1000);
+        v.shrink_free_list();
+        assert_eq!(v.free.len(), 10);
+        assert!(v.free.capacity() < 1000);
+        assert_eq!(v.bits.len(), 16);
+        assert_eq!(v.validate(), Ok(()));
+        assert_eq!(v.insert(0), 0);
+
+        v.resize_filled(500, 0);
+        v.shrink_to_fit();
+        assert_eq!(v.free.len(), 4);
+        assert_eq!(v.bits.len(), 8);
+        assert_eq!(v.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_shrink_to_fit_keeping() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), None, None, None]);
+
+        v.shrink_to_fit_keeping(4);
+        assert_eq!(v.slot_len(), 4);
+        assert_eq!(v.capacity(), 4);
+
+        v.shrink_to_fit_keeping(2);
+        assert_eq!(v.slot_len(), 3);
+
+        v.shrink_to_fit_keeping(5);
+        assert_eq!(v.slot_len(), 5);
+        assert_eq!(v.len(), 2);
+    }
+
+    #[test]
+    fn test_prune_to_density() {
+        let mut v = OptionVec::from(vec![
+            Some(0), None, None, Some(3), None, Some(5), None, Some(7), None]);
+
+        let before = v.clone();
+        let moves = v.prune_to_density(0.8);
+
+        assert!(v.density() >= 0.8);
+        assert_eq!(moves, vec![(7, 1), (5, 2)]);
+
+        for &(old, new) in &moves {
+            assert_eq!(v.get(new), before.get(old));
+        }
+        assert_eq!(v.inner(), &vec![Some(0), Some(7), Some(5), Some(3)]);
+
+        let mut v = OptionVec::<i32>::from(vec![None, None]);
+        assert_eq!(v.prune_to_density(1.0), vec![]);
+        assert_eq!(v.density(), 1.0);
+
+        let mut v = OptionVec::from(vec![Some(1), Some(2)]);
+        assert_eq!(v.prune_to_density(2.0), vec![]);
+    }
+
+    #[test]
+    fn test_compact_count() {
+        let mut v = OptionVec::from(vec![
+            None, Some(1), None, None, Some(2), None]);
+
+        assert_eq!(v.compact_count(), 4);
+        assert_eq!(v.inner(), &vec![Some(1), Some(2)]);
+        assert_eq!(v.compact_count(), 0);
+    }
+
+    #[test]
+    fn test_compact_with() {
+        let mut v = OptionVec::from(vec![
+            Some(0), None, Some(2), None, None, Some(5), None]);
+        let mut moves = Vec::new();
+
+        v.compact_with(|old, new| moves.push((old, new)));
+
+        assert_eq!(moves, vec![(2, 1), (5, 2)]);
+        assert_eq!(v.inner(), &vec![Some(0), Some(2), Some(5)]);
+    }
+
+    #[test]
+    fn test_compact_into_remap() {
+        let mut remap = Vec::new();
+
+        let mut v = OptionVec::from(vec![None, Some(1), None, Some(3)]);
+        v.compact_into_remap(&mut remap);
+
+        assert_eq!(v.inner(), &vec![Some(1), Some(3)]);
+        assert_eq!(remap, vec![None, Some(0), None, Some(1)]);
+
+        let mut v = OptionVec::from(vec![Some(0), None, Some(2)]);
+        v.compact_into_remap(&mut remap);
+
+        assert_eq!(v.inner(), &vec![Some(0), Some(2)]);
+        assert_eq!(remap, vec![Some(0), None, Some(1)]);
+    }
+
+    #[test]
+    fn test_compact_by() {
+        let mut v = OptionVec::from(vec![
+            Some(2), None, Some(5), None, Some(3)]);
+
+        let remap = v.compact_by(|a, b| b.cmp(a));
+
+        assert_eq!(v.inner(), &vec![Some(5), Some(3), Some(2)]);
+        assert_eq!(remap, vec![Some(2), None, Some(0), None, Some(1)]);
+    }
+
+    #[test]
+    fn test_swap_ranges() {
+        let mut v = OptionVec::from(vec![
+            Some(0), None, Some(2), Some(3), Some(4), None]);
+
+        v.swap_ranges(4..6, 0..2).unwrap();
+
+        assert_eq!(v.inner(), &vec![Some(4), None, Some(2), Some(3), Some(0), None]);
+    }
+
+    #[test]
+    fn test_swap_ranges_error() {
+        use super::RangeError;
+
+        let mut v = OptionVec::from(vec![Some(0), Some(1), Some(2), Some(3)]);
+
+        assert_eq!(v.swap_ranges(0..2, 1..3), Err(RangeError::Overlapping));
+        assert_eq!(v.swap_ranges(0..1, 2..4), Err(RangeError::LengthMismatch));
+        assert_eq!(v.swap_ranges(0..2, 3..5), Err(RangeError::OutOfBounds));
+
+        assert_eq!(v.inner(), &vec![Some(0), Some(1), Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn test_pop_back() {
+        let mut v = OptionVec::from(vec![
+            Some(1), Some(2)]);
+
+        assert_eq!(v.pop_back(), Some(2));
+        assert_eq!(v.pop_back(), Some(1));
+        assert_eq!(v.pop_back(), None);
+    }
+
+    #[test]
+    fn test_pop_front() {
+        let mut v = OptionVec::from(vec![
+            Some(1), Some(2)]);
+
+        assert_eq!(v.pop_front(), Some(1));
+        assert_eq!(v.pop_front(), Some(2));
+        assert_eq!(v.pop_front(), None);
+    }
+
+    #[test]
+    fn test_pop_indexed() {
+        let mut v = OptionVec::from(vec![
+            None, Some(1), Some(2), Some(3), None]);
+
+        assert_eq!(v.pop_front_indexed(), Some((1, 1)));
+        assert_eq!(v.pop_back_indexed(), Some((3, 3)));
+
+        assert_eq!(v.insert(4), 0);
+        assert_eq!(v.insert(5), 1);
+
+        assert_eq!(v.pop_front(), Some(4));
+        assert_eq!(v.insert(6), 0);
+    }
+
+    #[test]
+    fn test_swap_remove_front() {
+        let mut v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        assert_eq!(v.swap_remove_front(), Some(1));
+        assert_eq!(v.inner(), &vec![None, Some(3), Some(2), None, None, None]);
+
+        assert_eq!(v.swap_remove_front(), Some(3));
+        assert_eq!(v.inner(), &vec![None, Some(2), None, None, None, None]);
+
+        assert_eq!(v.swap_remove_front(), Some(2));
+        assert_eq!(v.swap_remove_front(), None);
+    }
+
+    #[test]
+    fn test_swap_remove_indexed_back() {
+        let mut v = OptionVec::from(vec![Some(1), Some(2), None, Some(3), None]);
+
+        assert_eq!(v.swap_remove_indexed_back(1), Some((2, Some((3, 1)))));
+        assert_eq!(v.inner(), &vec![Some(1), Some(3), None, None, None]);
+
+        assert_eq!(v.swap_remove_indexed_back(1), Some((3, None)));
+        assert_eq!(v.inner(), &vec![Some(1), None, None, None, None]);
+
+        assert_eq!(v.swap_remove_indexed_back(1), None);
+        assert_eq!(v.swap_remove_indexed_back(10), None);
+    }
+
+    #[test]
+    fn test_is_range_vacant() {
+        let v = OptionVec::from(vec![None, None, None, None, Some(1), None]);
+
+        assert!(v.is_range_vacant(0..4));
+        assert!(!v.is_range_vacant(2..6));
+        assert!(v.is_range_vacant(5..100));
+        assert!(v.is_range_vacant(50..));
+        assert!(!v.is_range_vacant(..));
+    }
+
+    #[test]
+    fn test_is_in_bounds() {
+        let v = OptionVec::from(vec![Some(1), None]);
+
+        assert!(v.is_in_bounds(0) && v.contains(0));
+        assert!(v.is_in_bounds(1) && !v.contains(1));
+        assert!(!v.is_in_bounds(2) && !v.contains(2));
+    }
+
+    #[test]
+    fn test_run_at() {
+        let v = OptionVec::from(vec![
+            Some(0), None, None, None, Some(4), Some(5), None]);
+
+        assert_eq!(v.vacant_run_at(1), 3);
+        assert_eq!(v.vacant_run_at(2), 2);
+        assert_eq!(v.vacant_run_at(0), 0);
+        assert_eq!(v.vacant_run_at(6), 1);
+        assert_eq!(v.vacant_run_at(10), 0);
+
+        assert_eq!(v.occupied_run_at(4), 2);
+        assert_eq!(v.occupied_run_at(1), 0);
+    }
+
+    #[test]
+    fn test_run_histogram() {
+        let v = OptionVec::from(vec![
+            Some(0), Some(1), None, Some(3), None, None, Some(6), Some(7), None]);
+
+        let (occupied, vacant) = v.run_histogram();
+
+        assert_eq!(occupied, vec![0, 1, 2]);
+        assert_eq!(vacant, vec![0, 2, 1]);
+
+        assert_eq!(OptionVec::<i32>::new().run_histogram(), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_occupied_bounds() {
+        let v = OptionVec::from(vec![
+            None, Some(1), None, Some(2), Some(3), None]);
+
+        assert_eq!(v.occupied_bounds(), Some((1, 4)));
+
+        let v = OptionVec::from(vec![None, Some(1), None]);
+        assert_eq!(v.occupied_bounds(), Some((1, 1)));
+
+        let v = OptionVec::<i32>::from(vec![None, None]);
+        assert_eq!(v.occupied_bounds(), None);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        let mut iter = v.into_iter();
+
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!(iter.next(), Some(3));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_into_iter_into_option_vec() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None, Some(4)]);
+
+        let mut iter = v.into_iter();
+        iter.next();
+        iter.next_back();
+
+        let v = iter.into_option_vec();
+        assert_eq!(v.inner(), &vec![Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn test_len_cached() {
+        use super::Action;
+
+        fn check<T>(v: &OptionVec<T>) {
+            let n = v.slots().iter().filter(|v| v.is_some()).count();
+
+            assert_eq!(v.len(), n);
+            assert_eq!(v.is_empty(), n == 0);
+        }
+
+        let mut v = OptionVec::from(vec![Some(1), None, Some(3)]);
+        check(&v);
+
+        v.insert(2);
+        v.insert(4);
+        v.insert_at(6, 6);
+        v.insert_at(6, 7);
+        check(&v);
+
+        v.remove(0);
+        v.remove(0);
+        v.pop_back();
+        v.swap_remove_front();
+        check(&v);
+
+        v.retain(|&mut x| x != 3);
+        v.retain_map(|x| if x == 4 { None } else { Some(x) });
+        v.update_retain(|i, _| if i == 0 { Action::Remove } else { Action::Keep });
+        check(&v);
+
+        v.resize_filled(5, 9);
+        v.allocate_contiguous(2, |i| i as i32);
+        v.resize_with_default(3);
+        check(&v);
+
+        v.try_replace_range(0..2, vec![None, Some(1)]).unwrap();
+        check(&v);
+
+        v.drain_keep_layout().next();
+        check(&v);
+
+        v.inner_mut().push(Some(10));
+        v.inner_mut()[0] = None;
+        check(&v);
+
+        let mut w = OptionVec::new();
+        w.clone_from(&v);
+        check(&w);
+        check(&v.clone());
+
+        assert_eq!(v.into_iter().len(), w.len());
+
+        w.clear();
+        check(&w);
+    }
+
+    #[test]
+    fn test_insert_first_vacant() {
+        fn check(v: &OptionVec<u32>) {
+            let expected = v.slots().iter().position(Option::is_none)
+                .unwrap_or(v.slot_len());
+
+            assert_eq!(v.clone().insert(0), expected);
+        }
+
+        let mut v = OptionVec::new();
+        let mut seed = 1u32;
+
+        for i in 0..500 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let n = (seed >> 16) as usize;
+
+            match n % 9 {
+                0 | 1 => { v.insert(i); }
+                2 => { v.remove(n % 40); }
+                3 => { v.insert_at(n % 50, i); }
+                4 => v.retain(|&mut x| x % 3 != 0),
+                5 => { let _ = v.swap_ranges(0..3, 10..13); }
+                6 => { let _ = v.shift_within(5..10, -2); }
+                7 => { v.drain_keep_layout().take(2).count(); }
+                _ => { v.inner_mut().truncate(n % 30); }
+            }
+
+            check(&v);
+        }
+    }
+
+    #[test]
+    fn test_occupancy_scans() {
+        fn check(v: &OptionVec<u32>) {
+            let values: Vec<_> = v.slots().iter().flatten().collect();
+            let first = v.slots().iter().position(Option::is_some);
+            let last = v.slots().iter().rposition(Option::is_some);
+
+            assert_eq!(v.iter().collect::<Vec<_>>(), values);
+            assert_eq!(v.iter().rev().collect::<Vec<_>>(),
+                values.iter().rev().cloned().collect::<Vec<_>>());
+            assert_eq!(v.occupied_bounds(), first.and_then(|f| last.map(|l| (f, l))));
+        }
+
+        let mut v = OptionVec::new();
+        let mut seed = 7u32;
+
+        for i in 0..500 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let n = (seed >> 16) as usize;
+
+            match n % 13 {
+                0 => { v.insert(i); }
+                1 | 2 => { v.remove(n % 200); }
+                3 => { v.insert_at(n % 200, i); }
+                4 => v.retain(|&mut x| x % 4 != 0),
+                5 => { let _ = v.shift_within(60..80, 7); }
+                6 => { v.swap_remove_indexed_back(n % 200); }
+                7 => { v.prune_to_density(0.5); }
+                8 => v.resize_filled(n % 150, i),
+                9 => v.resize_with_default(n % 150),
+                10 => v.shrink_to_fit_keeping(n % 200),
+                11 => { v.extend(0..n as u32 % 40); }
+                _ => { v.drain_keep_layout().rev().take(3).count(); }
+            }
+
+            check(&v);
+        }
+
+        let mut v: OptionVec<u32> = (0..20).collect();
+
+        v.shrink_to_fit_keeping(100);
+        v.resize_filled(72, 7);
+        check(&v);
+        assert_eq!(v.len(), 20);
+        assert_eq!(v.pop_back(), Some(19));
+        assert_eq!(v.clone().iter().count(), 19);
+
+        let mut v = OptionVec::from_sorted_pairs(vec![(3, 3), (70, 70), (140, 140)]);
+        let mut iter = v.iter();
+
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.next_back(), Some(&140));
+        assert_eq!(iter.as_slice().len(), 136);
+        assert_eq!(iter.next(), Some(&70));
+        assert_eq!(iter.last_yielded_index(), Some(70));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.as_slice().len(), 0);
+
+        assert_eq!(v.drain_keep_layout().collect::<Vec<_>>(),
+            vec![(3, 3), (70, 70), (140, 140)]);
+        assert_eq!(v.first_occupied(), None);
+        assert_eq!(v.slot_len(), 141);
+    }
+
+    #[test]
+    fn test_into_iter_exact() {
+        let v = OptionVec::from(vec![
+            None, Some(1), None, None, Some(2), Some(3), None, Some(4), Some(5)]);
+        let n = v.occupied_len();
+
+        let iter = v.into_iter_exact();
+        assert_eq!(iter.len(), n);
+
+        let collected: Vec<_> = iter.collect();
+        assert_eq!(collected, vec![1, 2, 3, 4, 5]);
+        assert_eq!(collected.capacity(), n);
+    }
+
+    #[test]
+    fn test_niche_layout() {
+        use std::mem::size_of;
+        use std::num::NonZeroU32;
+
+        assert_eq!(size_of::<Option<NonZeroU32>>(), size_of::<u32>());
+
+        let v: OptionVec<NonZeroU32> = OptionVec::from(vec![
+            NonZeroU32::new(1), None, NonZeroU32::new(3)]);
+
+        assert_eq!(size_of_val(v.slots()), 3 * size_of::<u32>());
+    }
 
-impl<T: Clone> Clone for OptionVec<T> {
-    fn clone(&self) -> OptionVec<T> {
-        let end = self.end_occupied();
+    #[test]
+    fn test_update_retain() {
+        use super::Action;
+
+        let mut v = OptionVec::from(vec![Some(1), None, Some(2), Some(3), Some(4)]);
+
+        v.update_retain(|i, x| {
+            match i {
+                0 => { *x += 10; Action::Keep }
+                2 => Action::Remove,
+                3 => Action::Replace(30),
+                _ => Action::Keep
+            }
+        });
 
-        OptionVec::from(self.vec[..end].to_vec())
+        assert_eq!(v.inner(), &vec![Some(11), None, None, Some(30), Some(4)]);
     }
 
-    fn clone_from(&mut self, other: &OptionVec<T>) {
-        let end = other.end_occupied();
+    #[test]
+    fn test_into_sorted_vec() {
+        let v = OptionVec::from(vec![Some(3), None, Some(1), None, Some(2)]);
 
-        self.vec.truncate(end);
-        let len = self.vec.len();
+        assert_eq!(v.clone().into_sorted_vec(), vec![1, 2, 3]);
+        assert_eq!(v.clone().into_sorted_by(|a, b| b.cmp(a)), vec![3, 2, 1]);
+        assert_eq!(v.into_sorted_by_key(|&x| x % 3), vec![3, 1, 2]);
+    }
 
-        self.vec.clone_from_slice(&other.vec[..len]);
-        self.vec.extend_from_slice(&other.vec[len..end]);
+    #[test]
+    fn test_chunk_by() {
+        let v = OptionVec::from(vec![
+            Some(1), Some(3), Some(2), None, Some(4), Some(6), None, Some(8), Some(5)]);
+
+        let groups: Vec<_> = v.chunk_by(|x| x % 2).collect();
+
+        assert_eq!(groups, vec![
+            vec![(0, &1), (1, &3)],
+            vec![(2, &2)],
+            vec![(4, &4), (5, &6)],
+            vec![(7, &8)],
+            vec![(8, &5)],
+        ]);
     }
-}
 
-impl<T> Default for OptionVec<T> {
-    fn default() -> OptionVec<T> {
-        OptionVec::new()
+    #[test]
+    fn test_shift_within() {
+        let mut v = OptionVec::from(vec![Some(1), Some(2), None, Some(3), None, None]);
+
+        assert_eq!(v.shift_within(1..4, 2), Ok(()));
+        assert_eq!(v.inner(), &vec![Some(1), None, None, Some(2), None, Some(3)]);
+
+        assert_eq!(v.shift_within(3..6, -2), Ok(()));
+        assert_eq!(v.inner(), &vec![Some(1), Some(2), None, Some(3), None, None]);
     }
-}
 
-impl<T> From<Vec<Option<T>>> for OptionVec<T> {
-    fn from(v: Vec<Option<T>>) -> OptionVec<T> {
-        OptionVec{vec: v}
+    #[test]
+    fn test_shift_within_error() {
+        use super::ShiftError;
+
+        let mut v = OptionVec::from(vec![Some(1), Some(2), None, Some(3), None]);
+
+        assert_eq!(v.shift_within(1..3, 2), Err(ShiftError::Collision(3)));
+        assert_eq!(v.shift_within(0..2, -1), Err(ShiftError::OutOfBounds));
+        assert_eq!(v.shift_within(3..4, 2), Err(ShiftError::OutOfBounds));
+        assert_eq!(v.shift_within(3..6, 1), Err(ShiftError::OutOfBounds));
+        assert_eq!(v.inner(), &vec![Some(1), Some(2), None, Some(3), None]);
     }
-}
 
-impl<T> Into<Vec<Option<T>>> for OptionVec<T> {
-    fn into(self) -> Vec<Option<T>> {
-        self.vec
+    #[test]
+    fn test_try_replace_range() {
+        use super::ReplaceError;
+
+        let mut v = OptionVec::from(vec![Some(1), None, Some(3), Some(4)]);
+
+        assert_eq!(v.try_replace_range(1..3, vec![Some(2)]),
+            Err(ReplaceError::LengthMismatch));
+        assert_eq!(v.try_replace_range(3..5, vec![None, None]),
+            Err(ReplaceError::OutOfBounds));
+        assert_eq!(v.inner(), &vec![Some(1), None, Some(3), Some(4)]);
+
+        assert_eq!(v.try_replace_range(1..=2, vec![Some(2), None]),
+            Ok(vec![None, Some(3)]));
+        assert_eq!(v.inner(), &vec![Some(1), Some(2), None, Some(4)]);
+    }
+
+    #[test]
+    fn test_drain_keep_layout() {
+        let mut v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        assert_eq!(v.drain_keep_layout().collect::<Vec<_>>(),
+            vec![(1, 1), (2, 2), (4, 3)]);
+        assert_eq!(v.slot_len(), 6);
+        assert!(v.is_empty());
+
+        let mut v = OptionVec::from(vec![Some(1), Some(2), Some(3)]);
+
+        assert_eq!(v.drain_keep_layout().next(), Some((0, 1)));
+        assert_eq!(v.inner(), &vec![None, None, None]);
+    }
+
+    #[test]
+    fn test_into_iter_rev() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        let mut fwd = v.clone().into_iter().collect::<Vec<_>>();
+        fwd.reverse();
+
+        assert_eq!(v.into_iter_rev().collect::<Vec<_>>(), fwd);
+    }
+
+    #[test]
+    fn test_into_iter_boxed() {
+        let v = Box::new(OptionVec::from(vec![
+            None, Some(1), Some(2), None]));
+
+        let mut res = Vec::new();
+
+        for n in v {
+            res.push(n);
+        }
+
+        assert_eq!(res, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_iter() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        let mut iter = v.iter();
+
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_iter_last_yielded_index() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None, Some(4), None]);
+
+        let mut iter = v.iter();
+        assert_eq!(iter.last_yielded_index(), None);
+
+        iter.next();
+        assert_eq!(iter.last_yielded_index(), Some(1));
+
+        iter.next();
+        iter.next();
+        assert_eq!(iter.last_yielded_index(), Some(4));
+
+        iter.next_back();
+        assert_eq!(iter.last_yielded_index(), Some(6));
+
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.last_yielded_index(), Some(6));
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "no element at index 1"))]
+    fn test_iter_stale_bit() {
+        let mut v = OptionVec::from(vec![Some(0), None, Some(2), None, Some(4)]);
+        v.bits[0] |= 0b1010;
+
+        assert_eq!(v.iter().collect::<Vec<_>>(), [&0, &2, &4]);
+        assert_eq!(v.iter().rev().collect::<Vec<_>>(), [&4, &2, &0]);
+    }
+
+    #[test]
+    fn test_iter_size_hint() {
+        let mut v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), Some(4), None, Some(5)]);
+
+        let mut iter = v.iter();
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.len(), 3);
+
+        let mut iter = v.iter_mut();
+        iter.next_back();
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+
+        let (a, b) = v.iter_mut().split();
+        assert_eq!((a.len(), b.len()), (2, 3));
+
+        assert_eq!(v.iter().collect::<Vec<_>>().capacity(), v.len());
+        assert_eq!(v.iter_mut().collect::<Vec<_>>().capacity(), v.len());
+    }
+
+    #[test]
+    fn test_iter_count() {
+        let mut v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        assert_eq!(v.iter().count(), 3);
+        assert_eq!(v.iter_mut().count(), 3);
+
+        let mut iter = v.iter();
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.count(), 1);
+
+        let mut iter = v.iter_mut();
+        iter.next();
+        assert_eq!(iter.count(), 2);
+
+        v.remove(2);
+        assert_eq!(v.iter().count(), 2);
+        assert_eq!(v.iter_mut().count(), 2);
+
+        assert_eq!(v.clone().into_keyed_iter().count(), 2);
+
+        let mut iter = v.clone().into_keyed_iter();
+        iter.next_back();
+        assert_eq!(iter.count(), 1);
+
+        let mut iter = v.clone().into_iter();
+        iter.next();
+        assert_eq!(iter.count(), 1);
+
+        assert_eq!(v.into_iter().count(), 2);
+    }
+
+    #[test]
+    fn test_iter_as_slice() {
+        let v = OptionVec::from(vec![
+            None, Some(1), None, Some(2)]);
+
+        let mut iter = v.iter();
+        assert_eq!(iter.as_slice().len(), 4);
+
+        iter.next();
+        assert_eq!(iter.as_slice(), &[None, Some(2)]);
+
+        let mut iter = v.into_iter();
+        iter.next_back();
+        assert_eq!(iter.as_slice(), &[None, Some(1), None]);
+
+        iter.next();
+        assert_eq!(iter.as_slice(), &[None]);
+    }
+
+    #[test]
+    fn test_iter_rev() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        let mut iter = v.iter().rev();
+
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_iter_rfind() {
+        let v = OptionVec::from(vec![
+            Some(1), None, Some(4), Some(2), None, Some(6), Some(3), None]);
+
+        let mut calls = 0;
+        let found = v.iter().rev().find(|&&n| {
+            calls += 1;
+            n % 2 == 0
+        });
+
+        assert_eq!(found, Some(&6));
+        assert_eq!(calls, 2);
+
+        assert_eq!(v.iter().rfold(0, |acc, &n| acc * 10 + n), 36241);
+        assert_eq!(v.enumerate().rev().find(|&(_, &n)| n < 3), Some((3, &2)));
+        assert_eq!(v.into_iter().rfold(Vec::new(), |mut acc, n| { acc.push(n); acc }),
+            vec![3, 6, 2, 4, 1]);
+    }
+
+    #[test]
+    fn test_iter_mut() {
+        let mut v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        for i in &mut v {
+            *i *= 2;
+        }
+
+        let mut iter = v.iter();
+
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&4));
+        assert_eq!(iter.next(), Some(&6));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_iter_mut_split() {
+        let mut v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None, Some(4)]);
+
+        let (a, b) = v.iter_mut().split();
+
+        for i in a {
+            *i += 10;
+        }
+        for i in b {
+            *i += 20;
+        }
+
+        assert_eq!(v.inner(), &vec![
+            None, Some(11), Some(12), None, Some(23), None, Some(24)]);
+    }
+
+    #[test]
+    fn test_into_enumerate() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        let mut iter = v.into_enumerate();
+
+        assert_eq!(iter.next(), Some((1, 1)));
+        assert_eq!(iter.next(), Some((2, 2)));
+        assert_eq!(iter.next(), Some((4, 3)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_iter_range() {
+        let v = OptionVec::from(vec![
+            Some(0), Some(1), Some(2), None, Some(4), None, Some(6), Some(7), Some(8)]);
+
+        assert_eq!(v.iter_range(2..7).collect::<Vec<_>>(),
+            vec![(2, &2), (4, &4), (6, &6)]);
+        assert_eq!(v.iter_range(2..7).next_back(), Some((6, &6)));
+        assert_eq!(v.iter_range(7..).collect::<Vec<_>>(), vec![(7, &7), (8, &8)]);
+        assert_eq!(v.iter_range(..=1).collect::<Vec<_>>(), vec![(0, &0), (1, &1)]);
+        assert_eq!(v.iter_range(20..30).count(), 0);
+    }
+
+    #[test]
+    fn test_indices_range() {
+        let v = OptionVec::from(vec![
+            Some(0), None, Some(2), Some(3), None, None, Some(6), Some(7)]);
+
+        assert_eq!(v.occupied_indices_range(1..7).collect::<Vec<_>>(), vec![2, 3, 6]);
+        assert_eq!(v.occupied_indices_range(1..7).next_back(), Some(6));
+        assert_eq!(v.vacant_indices_range(1..7).collect::<Vec<_>>(), vec![1, 4, 5]);
+        assert_eq!(v.vacant_indices_range(5..20).collect::<Vec<_>>(), vec![5]);
+    }
+
+    #[test]
+    fn test_iter_positioned() {
+        let v = OptionVec::from(vec![
+            None, Some("a"), None, None, Some("b"), Some("c")]);
+
+        assert_eq!(v.iter_positioned().collect::<Vec<_>>(),
+            vec![(0, 1, &"a"), (1, 4, &"b"), (2, 5, &"c")]);
+    }
+
+    #[test]
+    fn test_into_keyed_iter() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+        let n = v.occupied_len();
+
+        let mut iter = v.into_keyed_iter();
+        assert_eq!(iter.len(), n);
+
+        assert_eq!(iter.next(), Some((1, 1)));
+        assert_eq!(iter.next_back(), Some((4, 3)));
+        assert_eq!(iter.len(), 1);
+
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(2, 2)]);
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        let mut iter = v.enumerate();
+
+        assert_eq!(iter.next(), Some((1, &1)));
+        assert_eq!(iter.next(), Some((2, &2)));
+        assert_eq!(iter.next(), Some((4, &3)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_enumerate_sparse() {
+        let mut v = OptionVec::new();
+        v.insert_at(1000, 'a');
+        v.insert_at(70, 'b');
+        v.insert_at(3, 'c');
+
+        let mut iter = v.enumerate();
+
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next_back(), Some((1000, &'a')));
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(iter.clone().collect::<Vec<_>>(), [(3, &'c'), (70, &'b')]);
+        assert_eq!(iter.next(), Some((3, &'c')));
+        assert_eq!(iter.next_back(), Some((70, &'b')));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn test_enumerate_rev() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        let mut iter = v.enumerate().rev();
+
+        assert_eq!(iter.next(), Some((4, &3)));
+        assert_eq!(iter.next(), Some((2, &2)));
+        assert_eq!(iter.next(), Some((1, &1)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_enumerate_mut() {
+        let mut v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
+
+        for (n, v) in v.enumerate_mut() {
+            *v += n;
+        }
+
+        let mut iter = v.into_enumerate();
+
+        assert_eq!(iter.next(), Some((1, 2)));
+        assert_eq!(iter.next(), Some((2, 4)));
+        assert_eq!(iter.next(), Some((4, 7)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_for_each_index_mut() {
+        let mut v = OptionVec::from(vec![
+            Some(1), None, Some(2), Some(3), Some(4)]);
+        let mut seen = Vec::new();
+
+        v.for_each_index_mut(vec![4, 1, 2, 9], |i, n| {
+            seen.push(i);
+            *n *= 10;
+        });
+
+        assert_eq!(seen, vec![4, 2]);
+        assert_eq!(v.inner(), &vec![Some(1), None, Some(20), Some(3), Some(40)]);
     }
-}
 
-impl<T> Extend<T> for OptionVec<T> {
-    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=T> {
-        let iter = iter.into_iter();
+    #[test]
+    fn test_index_mut_checked() {
+        use super::GetError;
 
-        let (low, _) = iter.size_hint();
-        self.reserve(low);
+        let mut v = OptionVec::from(vec![Some(1), None]);
 
-        for v in iter {
-            self.insert(v);
-        }
-    }
-}
+        *v.index_mut_checked(0).unwrap() += 1;
+        assert_eq!(v.get(0), Some(&2));
 
-impl<T> FromIterator<T> for OptionVec<T> {
-    fn from_iter<I>(iter: I) -> OptionVec<T> where I: IntoIterator<Item=T> {
-        OptionVec{vec: iter.into_iter().map(Some).collect()}
+        assert_eq!(v.index_mut_checked(1), Err(GetError::Empty(1)));
+        assert_eq!(v.index_mut_checked(2), Err(GetError::OutOfBounds(2)));
     }
-}
 
-macro_rules! impl_eq {
-    ( $rhs:ty ) => {
-        impl<'b, A, B> PartialEq<$rhs> for OptionVec<A> where A: PartialEq<B> {
-            #[inline]
-            fn eq(&self, rhs: &$rhs) -> bool { self.iter().eq(rhs.iter()) }
-            #[inline]
-            fn ne(&self, rhs: &$rhs) -> bool { self.iter().ne(rhs.iter()) }
-        }
-    }
-}
+    #[test]
+    fn test_entry() {
+        use super::Entry;
 
-impl_eq!{ OptionVec<B> }
-impl_eq!{ Vec<B> }
-impl_eq!{ &'b [B] }
+        let mut v = OptionVec::from(vec![Some(1), None]);
 
-impl<T> Eq for OptionVec<T> where T: Eq {}
+        *v.entry(0).or_insert(10) += 1;
+        *v.entry(1).or_insert(20) += 1;
 
-impl<T> PartialOrd for OptionVec<T> where T: PartialOrd {
-    #[inline]
-    fn partial_cmp(&self, rhs: &OptionVec<T>) -> Option<Ordering> {
-        self.iter().partial_cmp(rhs.iter())
-    }
+        assert_eq!(v.inner(), &vec![Some(2), Some(21)]);
 
-    #[inline]
-    fn lt(&self, rhs: &OptionVec<T>) -> bool { self.iter().lt(rhs.iter()) }
-    #[inline]
-    fn le(&self, rhs: &OptionVec<T>) -> bool { self.iter().le(rhs.iter()) }
-    #[inline]
-    fn gt(&self, rhs: &OptionVec<T>) -> bool { self.iter().gt(rhs.iter()) }
-    #[inline]
-    fn ge(&self, rhs: &OptionVec<T>) -> bool { self.iter().ge(rhs.iter()) }
-}
+        match v.entry(0) {
+            Entry::Occupied(e) => assert_eq!(e.remove(), 2),
+            Entry::Vacant(_) => panic!("expected occupied entry"),
+        }
 
-impl<T> Ord for OptionVec<T> where T: Ord {
-    #[inline]
-    fn cmp(&self, rhs: &OptionVec<T>) -> Ordering {
-        self.iter().cmp(rhs.iter())
+        assert_eq!(v.entry(0).key(), 0);
+        assert!(!v.contains(0));
     }
-}
 
-impl<T> ops::Index<usize> for OptionVec<T> {
-    type Output = T;
+    #[test]
+    fn test_entry_or_insert_with_key() {
+        let mut v = OptionVec::from(vec![Some(1)]);
 
-    #[inline]
-    fn index(&self, idx: usize) -> &T {
-        self.vec[idx].as_ref().unwrap_or_else(|| panic!("index {} is empty", idx))
-    }
-}
+        assert_eq!(*v.entry(0).or_insert_with_key(|i| i * 100), 1);
+        assert_eq!(*v.entry(3).or_insert_with_key(|i| i * 100), 300);
 
-impl<T> ops::IndexMut<usize> for OptionVec<T> {
-    #[inline]
-    fn index_mut(&mut self, idx: usize) -> &mut T {
-        self.vec[idx].as_mut().unwrap_or_else(|| panic!("index {} is empty", idx))
+        assert_eq!(v.inner(), &vec![Some(1), None, None, Some(300)]);
     }
-}
-
-impl<T> IntoIterator for OptionVec<T> {
-    type Item = T;
-    type IntoIter = IntoIter<T>;
 
-    #[inline]
-    fn into_iter(self) -> IntoIter<T> {
-        IntoIter(self.vec.into_iter())
-    }
-}
+    #[test]
+    fn test_get_many() {
+        use super::GetError;
 
-impl<'a, T> IntoIterator for &'a OptionVec<T> {
-    type Item = &'a T;
-    type IntoIter = Iter<'a, T>;
+        let v = OptionVec::from(vec![Some("a"), None, Some("b")]);
 
-    #[inline]
-    fn into_iter(self) -> Iter<'a, T> {
-        self.iter()
+        assert_eq!(v.get_many([2, 0, 2]), Ok([&"b", &"a", &"b"]));
+        assert_eq!(v.get_many([0, 1, 3]), Err(GetError::Empty(1)));
+        assert_eq!(v.get_many([3, 1]), Err(GetError::OutOfBounds(3)));
     }
-}
 
-impl<'a, T> IntoIterator for &'a mut OptionVec<T> {
-    type Item = &'a mut T;
-    type IntoIter = IterMut<'a, T>;
+    #[test]
+    fn test_merge_sorted() {
+        let a = OptionVec::from(vec![Some(1), None, Some(4), Some(6)]);
+        let b = OptionVec::from(vec![None, Some(2), Some(4), None, Some(9)]);
 
-    #[inline]
-    fn into_iter(self) -> IterMut<'a, T> {
-        self.iter_mut()
-    }
-}
+        let v = a.merge_sorted(b);
 
-#[cfg(test)]
-mod test {
-    use super::OptionVec;
+        assert_eq!(v.inner(), &vec![
+            Some(1), Some(2), Some(4), Some(4), Some(6), Some(9)]);
+    }
 
     #[test]
-    fn test_len() {
-        let v = OptionVec::from(vec![
-            None, Some("foo"), None, Some("bar"), None]);
+    fn test_rehome() {
+        let mut v = OptionVec::from(vec![
+            Some("a"), None, Some("b"), Some("c")]);
 
-        assert_eq!(v.len(), 2);
+        let mapping = [(0, 4), (2, 0), (3, 1)].iter().cloned().collect();
+        v.rehome(&mapping).unwrap();
+
+        assert_eq!(v.inner(), &vec![Some("b"), Some("c"), None, None, Some("a")]);
     }
 
     #[test]
-    fn test_insert() {
+    fn test_rehome_error() {
+        use super::RehomeError;
+
         let mut v = OptionVec::from(vec![
-            Some(()), None, Some(())]);
+            Some("a"), None, Some("b"), Some("c")]);
 
-        assert_eq!(v.len(), 2);
+        let mapping = [(0, 1), (2, 1), (3, 2)].iter().cloned().collect();
+        assert_eq!(v.rehome(&mapping), Err(RehomeError::Collision(1)));
 
-        assert_eq!(v.insert(()), 1);
-        assert_eq!(v.len(), 3);
+        let mapping = [(0, 1), (3, 2)].iter().cloned().collect();
+        assert_eq!(v.rehome(&mapping), Err(RehomeError::Missing(2)));
 
-        assert_eq!(v.insert(()), 3);
-        assert_eq!(v.len(), 4);
+        assert_eq!(v.inner(), &vec![Some("a"), None, Some("b"), Some("c")]);
     }
 
     #[test]
-    fn test_remove() {
+    fn test_map_indices() {
         let mut v = OptionVec::from(vec![
-            Some(1), Some(2), Some(3)]);
+            Some("a"), None, Some("b"), Some("c")]);
 
-        assert_eq!(v.remove(0), Some(1));
-        assert_eq!(v.remove(0), None);
+        v.map_indices(|i| 6 - i * 2).unwrap();
+
+        assert_eq!(v.inner(), &vec![
+            Some("c"), None, Some("b"), None, None, None, Some("a")]);
     }
 
     #[test]
-    fn test_retain() {
-        let mut v = OptionVec::from(vec![
-            Some(1), Some(2), Some(3)]);
-
-        v.retain(|n| *n >= 2);
+    #[should_panic(expected = "capacity overflow")]
+    fn test_map_indices_overflow() {
+        let mut v = OptionVec::from(vec![Some("a")]);
 
-        assert_eq!(v.len(), 2);
+        let _ = v.map_indices(|_| usize::MAX);
     }
 
     #[test]
-    fn test_clone() {
-        let a = OptionVec::from(vec![
-            Some(1), None, Some(2), None]);
+    fn test_map_indices_error() {
+        use super::IndexMapError;
 
-        let b = a.clone();
+        let mut v = OptionVec::from(vec![
+            Some("a"), None, Some("b"), Some("c")]);
 
-        let mut c = OptionVec::new();
-        c.clone_from(&a);
+        assert_eq!(v.map_indices(|i| i / 2), Err(IndexMapError::Collision(1)));
+        assert_eq!(v.inner(), &vec![Some("a"), None, Some("b"), Some("c")]);
+    }
 
-        let mut d = OptionVec::from(vec![Some(0); 10]);
-        d.clone_from(&a);
+    #[test]
+    fn test_iter_with_holes() {
+        let v = OptionVec::from(vec![
+            None, Some(1), None, Some(2)]);
 
-        assert_eq!(a.len(), 2);
-        assert_eq!(b.len(), 2);
-        assert_eq!(c.len(), 2);
-        assert_eq!(d.len(), 2);
+        let slots = v.iter_with_holes().collect::<Vec<_>>();
 
-        assert_eq!(a.inner().len(), 4);
-        assert_eq!(b.inner().len(), 3);
-        assert_eq!(c.inner().len(), 3);
-        assert_eq!(d.inner().len(), 3);
+        assert_eq!(slots, vec![(0, None), (1, Some(&1)), (2, None), (3, Some(&2))]);
+        assert_eq!(v.iter_with_holes().next_back(), Some((3, Some(&2))));
     }
 
     #[test]
-    fn test_shrink_to_fit() {
-        let mut v = OptionVec::from(vec![
-            Some(1), None, Some(2), None, None]);
+    fn test_flat_map_indexed() {
+        let v = OptionVec::from(vec![
+            Some(1), None, Some(2)]);
 
-        assert_eq!(v.len(), 2);
-        assert_eq!(v.inner().len(), 5);
+        let w = v.flat_map_indexed(|i, &n| vec![(i * 2, n), (i * 2 + 1, n * 10)]);
 
-        v.shrink_to_fit();
+        assert_eq!(w.inner(), &vec![
+            Some(1), Some(10), None, None, Some(2), Some(20)]);
 
-        assert_eq!(v.len(), 2);
-        assert_eq!(v.inner().len(), 3);
+        let w = v.flat_map_indexed(|_, &n| Some((0, n)));
+        assert_eq!(w.inner(), &vec![Some(2)]);
     }
 
     #[test]
-    fn test_pop_back() {
-        let mut v = OptionVec::from(vec![
-            Some(1), Some(2)]);
+    fn test_try_find() {
+        let v = OptionVec::from(vec![
+            None, Some("1"), Some("22"), Some("x"), Some("333")]);
 
-        assert_eq!(v.pop_back(), Some(2));
-        assert_eq!(v.pop_back(), Some(1));
-        assert_eq!(v.pop_back(), None);
+        let parse = |s: &&str| s.parse::<i32>().map(|n| n > 10);
+
+        assert_eq!(v.try_find(&parse), Ok(Some((2, &"22"))));
+        assert_eq!(v.try_find(|s| parse(s).map(|_| false)).map_err(|_| ()), Err(()));
+        assert_eq!(v.try_find(|_| Ok::<_, ()>(false)), Ok(None));
     }
 
     #[test]
-    fn test_pop_front() {
-        let mut v = OptionVec::from(vec![
-            Some(1), Some(2)]);
+    fn test_join() {
+        let v = OptionVec::from(vec![
+            None, Some(1), Some(2), None, Some(3), None]);
 
-        assert_eq!(v.pop_front(), Some(1));
-        assert_eq!(v.pop_front(), Some(2));
-        assert_eq!(v.pop_front(), None);
+        assert_eq!(v.join(", "), "1, 2, 3");
+        assert_eq!(OptionVec::<i32>::new().join(", "), "");
     }
 
     #[test]
-    fn test_into_iter() {
+    fn test_iter_min_max() {
+        use std::cmp::Ordering;
+
+        // Compares on the first field only, to observe tie-breaking
+        #[derive(Debug)]
+        struct Key(i32, char);
+
+        impl PartialEq for Key {
+            fn eq(&self, rhs: &Key) -> bool { self.0 == rhs.0 }
+        }
+        impl Eq for Key {}
+        impl PartialOrd for Key {
+            fn partial_cmp(&self, rhs: &Key) -> Option<Ordering> { Some(self.cmp(rhs)) }
+        }
+        impl Ord for Key {
+            fn cmp(&self, rhs: &Key) -> Ordering { self.0.cmp(&rhs.0) }
+        }
+
         let v = OptionVec::from(vec![
-            None, Some(1), Some(2), None, Some(3), None]);
+            None, Some(Key(2, 'a')), Some(Key(1, 'b')), None,
+            Some(Key(2, 'c')), Some(Key(1, 'd'))]);
 
-        let mut iter = v.into_iter();
+        assert_eq!(v.iter_min().map(|k| k.1), Some('b'));
+        assert_eq!(v.iter_max().map(|k| k.1), Some('a'));
 
-        assert_eq!(iter.next(), Some(1));
-        assert_eq!(iter.next(), Some(2));
-        assert_eq!(iter.next(), Some(3));
-        assert_eq!(iter.next(), None);
+        assert_eq!(OptionVec::<i32>::new().iter_min(), None);
     }
 
     #[test]
-    fn test_iter() {
+    fn test_argmax_by_key() {
         let v = OptionVec::from(vec![
-            None, Some(1), Some(2), None, Some(3), None]);
+            None, Some(-3), Some(1), None, Some(3), Some(-1)]);
 
-        let mut iter = v.iter();
+        assert_eq!(v.argmax_by_key(|n: &i32| n.abs()), Some(1));
+        assert_eq!(v.argmin_by_key(|n: &i32| n.abs()), Some(2));
+        assert_eq!(v.argmax_by_key(|&n| n), Some(4));
 
-        assert_eq!(iter.next(), Some(&1));
-        assert_eq!(iter.next(), Some(&2));
-        assert_eq!(iter.next(), Some(&3));
-        assert_eq!(iter.next(), None);
+        assert_eq!(OptionVec::<i32>::new().argmax_by_key(|&n| n), None);
     }
 
     #[test]
-    fn test_iter_rev() {
+    fn test_prefix_scan() {
         let v = OptionVec::from(vec![
-            None, Some(1), Some(2), None, Some(3), None]);
+            None, Some(1), Some(2), None, Some(3)]);
 
-        let mut iter = v.iter().rev();
+        assert_eq!(v.prefix_scan(0, |acc, n| acc + n),
+            vec![(1, 1), (2, 3), (4, 6)]);
+    }
 
-        assert_eq!(iter.next(), Some(&3));
-        assert_eq!(iter.next(), Some(&2));
-        assert_eq!(iter.next(), Some(&1));
-        assert_eq!(iter.next(), None);
+    #[test]
+    fn test_to_btreemap() {
+        let v = OptionVec::from(vec![
+            None, Some("a"), Some("b"), None, Some("c")]);
+
+        let map = v.to_btreemap();
+
+        assert_eq!(map.into_iter().collect::<Vec<_>>(),
+            vec![(1, &"a"), (2, &"b"), (4, &"c")]);
     }
 
     #[test]
-    fn test_iter_mut() {
-        let mut v = OptionVec::from(vec![
-            None, Some(1), Some(2), None, Some(3), None]);
+    fn test_from_btreemap() {
+        use std::collections::BTreeMap;
 
-        for i in &mut v {
-            *i *= 2;
-        }
+        let v = OptionVec::from(vec![
+            None, Some("a"), Some("b"), None, Some("c"), None]);
 
-        let mut iter = v.iter();
+        let map = v.to_btreemap().into_iter()
+            .map(|(i, &s)| (i, s)).collect::<BTreeMap<_, _>>();
+        let w = OptionVec::from(map);
 
-        assert_eq!(iter.next(), Some(&2));
-        assert_eq!(iter.next(), Some(&4));
-        assert_eq!(iter.next(), Some(&6));
-        assert_eq!(iter.next(), None);
+        // Trailing `None` positions are not preserved
+        assert_eq!(w.inner(), &v.inner()[..5]);
     }
 
     #[test]
-    fn test_into_enumerate() {
+    fn test_value_index_map() {
         let v = OptionVec::from(vec![
-            None, Some(1), Some(2), None, Some(3), None]);
+            Some("a"), None, Some("b"), Some("a"), None, Some("a")]);
 
-        let mut iter = v.into_enumerate();
+        let map = v.value_index_map();
 
-        assert_eq!(iter.next(), Some((1, 1)));
-        assert_eq!(iter.next(), Some((2, 2)));
-        assert_eq!(iter.next(), Some((4, 3)));
-        assert_eq!(iter.next(), None);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&"a"], vec![0, 3, 5]);
+        assert_eq!(map[&"b"], vec![2]);
     }
 
     #[test]
-    fn test_enumerate() {
+    fn test_duplicate_count() {
         let v = OptionVec::from(vec![
-            None, Some(1), Some(2), None, Some(3), None]);
+            Some("a"), None, Some("b"), Some("a"), None, Some("a"), Some("b"), Some("c")]);
 
-        let mut iter = v.enumerate();
+        assert_eq!(v.duplicate_count(), 3);
+        assert_eq!(OptionVec::from(vec![Some(1), None, Some(2)]).duplicate_count(), 0);
+    }
 
-        assert_eq!(iter.next(), Some((1, &1)));
-        assert_eq!(iter.next(), Some((2, &2)));
-        assert_eq!(iter.next(), Some((4, &3)));
-        assert_eq!(iter.next(), None);
+    #[test]
+    fn test_diff() {
+        use super::SlotDiff;
+
+        let a = OptionVec::from(vec![
+            Some(1), Some(2), None, Some(4)]);
+        let b = OptionVec::from(vec![
+            Some(1), None, Some(3), Some(5)]);
+
+        assert_eq!(a.diff(&b), vec![
+            SlotDiff::Removed(1),
+            SlotDiff::Added(2, &3),
+            SlotDiff::Changed(3, &5),
+        ]);
+        assert_eq!(a.diff(&a), vec![]);
     }
 
     #[test]
-    fn test_enumerate_rev() {
-        let v = OptionVec::from(vec![
-            None, Some(1), Some(2), None, Some(3), None]);
+    fn test_apply_diff() {
+        let mut a = OptionVec::from(vec![
+            Some(1), Some(2), None, Some(4), Some(5)]);
+        let b = OptionVec::from(vec![
+            Some(1), None, Some(3), Some(5), None, None, Some(7)]);
 
-        let mut iter = v.enumerate().rev();
+        a.apply_diff(a.diff(&b));
 
-        assert_eq!(iter.next(), Some((4, &3)));
-        assert_eq!(iter.next(), Some((2, &2)));
-        assert_eq!(iter.next(), Some((1, &1)));
-        assert_eq!(iter.next(), None);
+        assert_eq!(a.inner(), b.inner());
     }
 
     #[test]
-    fn test_enumerate_mut() {
+    fn test_indexed_mut() {
         let mut v = OptionVec::from(vec![
             None, Some(1), Some(2), None, Some(3), None]);
 
-        for (n, v) in v.enumerate_mut() {
-            *v += n;
+        for (n, v) in v.indexed_mut() {
+            *v *= n;
         }
 
-        let mut iter = v.into_enumerate();
-
-        assert_eq!(iter.next(), Some((1, 2)));
-        assert_eq!(iter.next(), Some((2, 4)));
-        assert_eq!(iter.next(), Some((4, 7)));
-        assert_eq!(iter.next(), None);
+        assert_eq!(v.inner(), &vec![None, Some(1), Some(4), None, Some(12), None]);
     }
 
     #[test]
@@ -799,4 +5302,36 @@ mod test {
         let _ = m.iter().clone();
         let _ = m.enumerate().clone();
     }
+
+    #[test]
+    fn test_gen_option_vec() {
+        use super::GenOptionVec;
+
+        let mut v = GenOptionVec::new();
+
+        let a = v.insert("a");
+        let b = v.insert("b");
+
+        assert_eq!(v.remove(a), Some("a"));
+
+        let c = v.insert("c");
+
+        assert_eq!(c.index, a.index);
+        assert!(c != a);
+        assert_eq!(v.get(a), None);
+        assert_eq!(v.remove(a), None);
+        assert_eq!(v.get(c), Some(&"c"));
+        assert_eq!(v[b], "b");
+        assert_eq!(v.len(), 2);
+
+        v[c] = "d";
+        assert_eq!(v.iter().collect::<Vec<_>>(), vec![(c, &"d"), (b, &"b")]);
+
+        v.clear();
+        let e = v.insert("e");
+
+        assert_eq!(e.index, c.index);
+        assert!(!v.contains(c));
+        assert!(v.contains(e));
+    }
 }